@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+/// The time warp multipliers the simulation can run at.
+/// Warp is always referred to by its index in this list.
+pub const WARP_LEVELS: [f64; 6] = [1., 10., 100., 1_000., 10_000., 100_000.];
+
+/// The simulation clock.
+/// Written by the physics thread, and sent to clients with every update.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Clock {
+    /// Simulated time, in seconds.
+    pub time: f64,
+
+    /// Number of fixed steps taken since the simulation started.
+    pub step: u64,
+
+    /// Index in `WARP_LEVELS` of the warp currently in effect.
+    pub warp: usize,
+
+    /// Index in `WARP_LEVELS` of the warp requested by clients.
+    /// The warp in effect can be lower, while ships are thrusting or close to planets.
+    pub requested_warp: usize,
+}
+
+impl Clock {
+    /// The time warp multiplier currently in effect,
+    /// or `None` if `warp` isn't an index in `WARP_LEVELS`, as from a server with more levels.
+    pub fn warp_factor(&self) -> Option<f64> {
+        WARP_LEVELS.get(self.warp).copied()
+    }
+}
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// Something notable that happened in the simulation.
+/// Events are raised by the physics engine, and broadcast to every client.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum Event {
+    /// A ship touched down gently on a planet's surface.
+    Landed { ship: i32, planet: i32 },
+
+    /// A landed ship lifted off a planet's surface.
+    LiftedOff { ship: i32, planet: i32 },
+
+    /// A ship hit a planet or another ship faster than the crash speed, and lost its fuel.
+    /// A ship that hit a planet rests on it as a wreck.
+    /// `other` is the id of the planet or ship that was hit.
+    Crashed { ship: i32, other: i32, speed: f64 },
+}
@@ -1,15 +1,57 @@
+use std::collections::HashMap;
+
 use serde::{Serialize, Deserialize};
 
 /// Dictates the desired rotation of the ship.
 /// The name "NavMode" is carried over from Orbit.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+///
+/// Directions are relative to the reference body, or to the target for the target modes.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
 pub enum NavMode {
+    /// Manual: the ship keeps spinning as it is.
+    #[default]
     MAN,
+    /// Stops the ship's spin, and holds its current heading.
+    HOLD,
+    /// Perpendicular to the reference, counter-clockwise.
+    CCWPROG,
+    /// Perpendicular to the reference, clockwise.
+    CWPROG,
+    /// Along the velocity relative to the reference.
+    PROG,
+    /// Against the velocity relative to the reference.
+    RETRO,
+    /// Away from the reference.
+    RADOUT,
+    /// Toward the reference.
+    RADIN,
+    /// Toward the target.
+    TTARG,
+    /// Away from the target.
+    ATARG,
 }
 
-/// Inputs given to the server by the client.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Inputs given to the server by the client, for a single ship.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
 pub struct Inputs {
     pub navmode: NavMode,
     pub throttle: f32,
+
+    /// Id of the body the ship navigates relative to.
+    /// When unset, the body with the strongest pull on the ship is used.
+    #[serde(default)]
+    pub reference: Option<i32>,
+
+    /// Id of the body used by the target nav modes.
+    #[serde(default)]
+    pub target: Option<i32>,
+}
+
+/// The inputs of every ship, keyed by ship id.
+/// Ships without an entry use the default inputs.
+pub type ShipInputs = HashMap<i32, Inputs>;
+
+/// Gets the inputs of a ship, or the default inputs if none were set.
+pub fn inputs_for(inputs: &ShipInputs, ship: i32) -> Inputs {
+    inputs.get(&ship).copied().unwrap_or_default()
 }
@@ -1,3 +1,6 @@
+pub mod clock;
+pub mod event;
 pub mod state;
 pub mod inputs;
 pub mod object;
+pub mod settings;
@@ -26,27 +26,125 @@ pub struct Object {
     /// A unique identifier for the object.
     pub id: i32,
 
+    /// The name shown next to the object. Optional in system files.
+    #[serde(default)]
+    pub name: String,
+
     /// The name of the texture for the object to use
     pub texture: String,
 }
 
+impl Object {
+    /// The name of the object, or its id when it has none.
+    pub fn label(&self) -> String {
+        if self.name.is_empty() {
+            format!("#{}", self.id)
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
 pub type Objects = (Vec<Ship>, Vec<Planet>);
 
 // ----------------- PLANETS -----------------
 
+/// An exponential atmosphere, rotating with its planet.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Atmosphere {
+    /// Air density at the surface, in kilograms per cubic meter.
+    pub sea_level_density: f64,
+
+    /// Altitude over which the density drops by a factor of e, in meters.
+    pub scale_height: f64,
+
+    /// Altitude above which there is no air, in meters.
+    pub ceiling: f64,
+}
+
+impl Atmosphere {
+    /// Air density at the given altitude above the surface, in kilograms per cubic meter.
+    pub fn density(&self, altitude: f64) -> f64 {
+        if altitude > self.ceiling {
+            return 0.;
+        }
+
+        self.sea_level_density * (-altitude.max(0.) / self.scale_height).exp()
+    }
+}
+
 /// A planet.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Planet {
     pub object: Object,
     pub radius: f64,
+
+    #[serde(default)]
+    pub atmosphere: Option<Atmosphere>,
 }
 
 // ----------------- SHIPS -----------------
 
+/// Standard gravity, used to turn specific impulse into exhaust velocity, in meters per second squared.
+pub const STANDARD_GRAVITY: f64 = 9.80665;
+
 /// Data specific to the HAB ship type.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct HabData {
+    /// Engine thrust at full throttle, in newtons.
     pub thrust: f32,
+
+    /// Mass of the ship without fuel, in kilograms.
+    pub dry_mass: f64,
+
+    /// Mass of the fuel left in the tank, in kilograms.
+    pub fuel_mass: f64,
+
+    /// Specific impulse of the engine, in seconds.
+    pub isp: f64,
+
+    /// Radius of the ship's collision circle, in meters.
+    pub radius: f64,
+
+    /// Drag coefficient, without units.
+    pub drag_coefficient: f64,
+
+    /// Area facing the airflow, in square meters.
+    pub cross_section: f64,
+
+    /// How fast the attitude thrusters can change the ship's spin, in radians per second squared.
+    pub max_angular_acceleration: f64,
+}
+
+impl HabData {
+    /// Current mass of the ship, fuel included, in kilograms.
+    pub fn mass(&self) -> f64 {
+        self.dry_mass + self.fuel_mass
+    }
+
+    /// Exhaust velocity of the engine, in meters per second.
+    pub fn exhaust_velocity(&self) -> f64 {
+        self.isp * STANDARD_GRAVITY
+    }
+
+    /// Fuel burnt per second at full throttle, in kilograms per second.
+    pub fn mass_flow(&self) -> f64 {
+        let exhaust_velocity = self.exhaust_velocity();
+        if exhaust_velocity > 0. {
+            self.thrust as f64 / exhaust_velocity
+        } else {
+            0.
+        }
+    }
+
+    /// Remaining delta-v, in meters per second, from the Tsiolkovsky rocket equation.
+    pub fn delta_v(&self) -> f64 {
+        if self.dry_mass <= 0. {
+            return 0.;
+        }
+
+        self.exhaust_velocity() * (self.mass() / self.dry_mass).ln()
+    }
 }
 
 /// Enum containing the ship type, and data attached to it.
@@ -57,9 +155,77 @@ pub enum ShipType {
     HAB(HabData),
 }
 
+/// Where a ship rests on a planet's surface.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Landing {
+    /// The id of the planet the ship is resting on.
+    pub planet: i32,
+
+    /// The ship's angle around the planet, relative to the planet's heading.
+    /// This stays constant while landed, so the ship turns with the planet.
+    pub angle: f64,
+}
+
 /// Any ship
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Ship {
     pub object: Object,
     pub ship_type: ShipType,
+
+    /// Set while the ship is resting on a planet's surface.
+    #[serde(default)]
+    pub landed: Option<Landing>,
+}
+
+impl Ship {
+    /// Current mass of the ship, fuel included, in kilograms.
+    pub fn mass(&self) -> f64 {
+        match &self.ship_type {
+            ShipType::HAB(data) => data.mass(),
+        }
+    }
+
+    /// Mass of the fuel left in the tank, in kilograms.
+    pub fn fuel_mass(&self) -> f64 {
+        match &self.ship_type {
+            ShipType::HAB(data) => data.fuel_mass,
+        }
+    }
+
+    /// Sets the fuel left in the tank, in kilograms.
+    /// `object.mass` is left as is.
+    pub fn set_fuel_mass(&mut self, fuel_mass: f64) {
+        match &mut self.ship_type {
+            ShipType::HAB(data) => data.fuel_mass = fuel_mass,
+        }
+    }
+
+    /// Remaining delta-v, in meters per second.
+    /// Shared by the server and clients, so both agree on what is left.
+    pub fn delta_v(&self) -> f64 {
+        match &self.ship_type {
+            ShipType::HAB(data) => data.delta_v(),
+        }
+    }
+
+    /// Radius of the ship's collision circle, in meters.
+    pub fn radius(&self) -> f64 {
+        match &self.ship_type {
+            ShipType::HAB(data) => data.radius,
+        }
+    }
+
+    /// How fast the ship's spin can change, in radians per second squared.
+    pub fn max_angular_acceleration(&self) -> f64 {
+        match &self.ship_type {
+            ShipType::HAB(data) => data.max_angular_acceleration,
+        }
+    }
+
+    /// Drag coefficient times cross section area, in square meters.
+    pub fn drag_area(&self) -> f64 {
+        match &self.ship_type {
+            ShipType::HAB(data) => data.drag_coefficient * data.cross_section,
+        }
+    }
 }
@@ -0,0 +1,75 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// The numerical integration scheme used to advance the simulation.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntegratorKind {
+    /// Leapfrog integration, Kick-Drift-Kick form.
+    #[default]
+    Leapfrog,
+    /// Velocity Verlet integration.
+    Verlet,
+    /// Classic fourth order Runge-Kutta.
+    RK4,
+    /// Runge-Kutta-Fehlberg 4(5), which picks its own step size.
+    RKF45,
+}
+
+impl FromStr for IntegratorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "leapfrog" => Ok(IntegratorKind::Leapfrog),
+            "verlet" => Ok(IntegratorKind::Verlet),
+            "rk4" => Ok(IntegratorKind::RK4),
+            "rkf45" => Ok(IntegratorKind::RKF45),
+            _ => Err(format!(
+                "unknown integrator '{s}', expected one of: leapfrog, verlet, rk4, rkf45"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for IntegratorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            IntegratorKind::Leapfrog => "leapfrog",
+            IntegratorKind::Verlet => "verlet",
+            IntegratorKind::RK4 => "rk4",
+            IntegratorKind::RKF45 => "rkf45",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Simulation settings loaded from the system file.
+/// Every field is optional in the file, and falls back to its default.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Settings {
+    /// The integrator used by the physics engine.
+    pub integrator: IntegratorKind,
+
+    /// Maximum local error per step, in meters, for adaptive integrators.
+    pub tolerance: f64,
+
+    /// Whether ships attract other ships and planets.
+    /// When off, ships feel gravity without exerting it, which is cheaper.
+    pub ship_gravity: bool,
+
+    /// Impacts faster than this, in meters per second, are crashes.
+    pub crash_speed: f64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            integrator: IntegratorKind::default(),
+            tolerance: 1e-3,
+            ship_gravity: false,
+            crash_speed: 10.,
+        }
+    }
+}
@@ -1,15 +1,21 @@
 use serde::{Serialize, Deserialize};
 
-use super::inputs::Inputs;
+use super::clock::Clock;
+use super::inputs::ShipInputs;
 use super::object::Object;
 use super::object::Planet;
 use super::object::Ship;
+use super::settings::Settings;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct State {
     pub ships: Vec<Ship>,
     pub planets: Vec<Planet>,
-    pub inputs: Inputs,
+    pub inputs: ShipInputs,
+    #[serde(default)]
+    pub settings: Settings,
+    #[serde(default)]
+    pub clock: Clock,
 }
 
 impl State {
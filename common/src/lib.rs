@@ -1,2 +1,3 @@
 pub mod data;
 pub mod messages;
+pub mod orbit;
\ No newline at end of file
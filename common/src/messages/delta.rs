@@ -0,0 +1,375 @@
+use serde::{Deserialize, Serialize};
+
+use crate::data::clock::Clock;
+use crate::data::inputs::ShipInputs;
+use crate::data::object::{Landing, Object};
+use crate::data::state::State;
+
+/// Quantization steps of the fields sent in deltas.
+/// Clients see values rounded to these, until the next keyframe.
+pub const POSITION_QUANTUM: f64 = 1e-3;
+pub const VELOCITY_QUANTUM: f64 = 1e-4;
+pub const ACCELERATION_QUANTUM: f64 = 1e-4;
+pub const HEADING_QUANTUM: f64 = 1e-6;
+pub const SPIN_QUANTUM: f64 = 1e-7;
+pub const MASS_QUANTUM: f64 = 1e-2;
+
+/// How many snapshots the server and clients keep as delta baselines.
+/// Both must keep the same number: the server only computes deltas from the baselines
+/// of its last `SNAPSHOT_HISTORY` ticks, which a client still has.
+/// Clients whose last ack is older than that get a keyframe.
+pub const SNAPSHOT_HISTORY: usize = 64;
+
+fn quantize(value: f64, quantum: f64) -> i64 {
+    (value / quantum).round() as i64
+}
+
+/// Difference between two values, in quanta.
+///
+/// Only depends on the quantized values, so a client holding a baseline that was itself
+/// rebuilt from deltas ends up with exactly the same quanta as the server.
+fn diff(baseline: f64, value: f64, quantum: f64) -> i64 {
+    quantize(value, quantum) - quantize(baseline, quantum)
+}
+
+fn undiff(baseline: f64, delta: i64, quantum: f64) -> f64 {
+    if delta == 0 {
+        return baseline;
+    }
+    (quantize(baseline, quantum) + delta) as f64 * quantum
+}
+
+/// The change of an object's moving parts since the baseline, in quanta.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ObjectDelta {
+    pub id: i32,
+    pub position: [i64; 2],
+    pub velocity: [i64; 2],
+    pub acceleration: [i64; 2],
+    pub heading: i64,
+    pub spin: i64,
+    pub mass: i64,
+}
+
+impl ObjectDelta {
+    fn unchanged(id: i32) -> ObjectDelta {
+        ObjectDelta {
+            id,
+            position: [0, 0],
+            velocity: [0, 0],
+            acceleration: [0, 0],
+            heading: 0,
+            spin: 0,
+            mass: 0,
+        }
+    }
+
+    /// Returns `None` when nothing visibly changed.
+    fn between(baseline: &Object, object: &Object) -> Option<ObjectDelta> {
+        let delta = ObjectDelta {
+            id: object.id,
+            position: [
+                diff(baseline.position[0], object.position[0], POSITION_QUANTUM),
+                diff(baseline.position[1], object.position[1], POSITION_QUANTUM),
+            ],
+            velocity: [
+                diff(baseline.velocity[0], object.velocity[0], VELOCITY_QUANTUM),
+                diff(baseline.velocity[1], object.velocity[1], VELOCITY_QUANTUM),
+            ],
+            acceleration: [
+                diff(baseline.acceleration[0], object.acceleration[0], ACCELERATION_QUANTUM),
+                diff(baseline.acceleration[1], object.acceleration[1], ACCELERATION_QUANTUM),
+            ],
+            heading: diff(baseline.heading, object.heading, HEADING_QUANTUM),
+            spin: diff(baseline.spin, object.spin, SPIN_QUANTUM),
+            mass: diff(baseline.mass, object.mass, MASS_QUANTUM),
+        };
+
+        let unchanged = delta.position == [0, 0]
+            && delta.velocity == [0, 0]
+            && delta.acceleration == [0, 0]
+            && delta.heading == 0
+            && delta.spin == 0
+            && delta.mass == 0;
+
+        if unchanged {
+            None
+        } else {
+            Some(delta)
+        }
+    }
+
+    fn apply(&self, object: &mut Object) {
+        for i in 0..2 {
+            object.position[i] = undiff(object.position[i], self.position[i], POSITION_QUANTUM);
+            object.velocity[i] = undiff(object.velocity[i], self.velocity[i], VELOCITY_QUANTUM);
+            object.acceleration[i] =
+                undiff(object.acceleration[i], self.acceleration[i], ACCELERATION_QUANTUM);
+        }
+        object.heading = undiff(object.heading, self.heading, HEADING_QUANTUM);
+        object.spin = undiff(object.spin, self.spin, SPIN_QUANTUM);
+        object.mass = undiff(object.mass, self.mass, MASS_QUANTUM);
+    }
+}
+
+/// The change of a ship since the baseline.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShipDelta {
+    pub object: ObjectDelta,
+    pub fuel_mass: i64,
+
+    /// Sent whole, as it is tiny and rarely set.
+    pub landed: Option<Landing>,
+}
+
+/// Whether two objects are the same body, as far as what deltas leave out goes.
+fn same_body(a: &Object, b: &Object) -> bool {
+    a.id == b.id && a.name == b.name && a.texture == b.texture
+}
+
+/// The change of a whole `State` since a baseline the client already has.
+///
+/// Bodies that didn't move are left out. A delta can only describe a state holding the
+/// same bodies as its baseline, with the same names, textures, radii and ship types.
+/// Anything else, such as a body being added, calls for a keyframe.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StateDelta {
+    pub ships: Vec<ShipDelta>,
+    pub planets: Vec<ObjectDelta>,
+
+    /// Only sent when they changed.
+    pub inputs: Option<ShipInputs>,
+    pub clock: Clock,
+}
+
+impl StateDelta {
+    /// Computes the delta from `baseline` to `state`.
+    /// Returns `None` if the bodies differ, in which case a keyframe must be sent.
+    pub fn between(baseline: &State, state: &State) -> Option<StateDelta> {
+        let same_ships = baseline.ships.len() == state.ships.len()
+            && baseline
+                .ships
+                .iter()
+                .zip(&state.ships)
+                .all(|(a, b)| same_body(&a.object, &b.object));
+        let same_planets = baseline.planets.len() == state.planets.len()
+            && baseline
+                .planets
+                .iter()
+                .zip(&state.planets)
+                .all(|(a, b)| same_body(&a.object, &b.object));
+
+        if !same_ships || !same_planets {
+            return None;
+        }
+
+        let ships = baseline
+            .ships
+            .iter()
+            .zip(&state.ships)
+            .filter_map(|(a, b)| {
+                let fuel_mass = diff(a.fuel_mass(), b.fuel_mass(), MASS_QUANTUM);
+                let object = ObjectDelta::between(&a.object, &b.object);
+                if object.is_none() && fuel_mass == 0 && b.landed.is_none() {
+                    return None;
+                }
+                Some(ShipDelta {
+                    object: object.unwrap_or_else(|| ObjectDelta::unchanged(b.object.id)),
+                    fuel_mass,
+                    landed: b.landed.clone(),
+                })
+            })
+            .collect();
+
+        let planets = baseline
+            .planets
+            .iter()
+            .zip(&state.planets)
+            .filter_map(|(a, b)| ObjectDelta::between(&a.object, &b.object))
+            .collect();
+
+        let inputs = if baseline.inputs == state.inputs {
+            None
+        } else {
+            Some(state.inputs.clone())
+        };
+
+        Some(StateDelta {
+            ships,
+            planets,
+            inputs,
+            clock: state.clock.clone(),
+        })
+    }
+
+    /// Rebuilds the state this delta was computed for, from the same baseline.
+    /// Returns `None` if the delta names a body the baseline doesn't have.
+    pub fn apply(&self, baseline: &State) -> Option<State> {
+        let mut state = baseline.clone();
+
+        // Ships left out of the delta didn't move, and aren't landed.
+        for ship in &mut state.ships {
+            ship.landed = None;
+        }
+
+        for delta in &self.ships {
+            let ship = state.ships.iter_mut().find(|s| s.object.id == delta.object.id)?;
+            delta.object.apply(&mut ship.object);
+            let fuel_mass = undiff(ship.fuel_mass(), delta.fuel_mass, MASS_QUANTUM);
+            ship.set_fuel_mass(fuel_mass);
+            ship.landed = delta.landed.clone();
+        }
+
+        for delta in &self.planets {
+            let planet = state.planets.iter_mut().find(|p| p.object.id == delta.id)?;
+            delta.apply(&mut planet.object);
+        }
+
+        if let Some(inputs) = &self.inputs {
+            state.inputs = inputs.clone();
+        }
+        state.clock = self.clock.clone();
+
+        Some(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use ndarray::array;
+
+    use super::*;
+    use crate::data::inputs::{Inputs, NavMode};
+    use crate::data::object::{HabData, Planet, Ship, ShipType};
+    use crate::messages::encoding::BincodeEncoder;
+
+    fn object(id: i32, mass: f64, position: [f64; 2], velocity: [f64; 2]) -> Object {
+        Object {
+            mass,
+            position: array![position[0], position[1]],
+            velocity: array![velocity[0], velocity[1]],
+            acceleration: array![0., 0.],
+            heading: 0.,
+            spin: 0.,
+            id,
+            name: format!("Body {}", id),
+            texture: String::new(),
+        }
+    }
+
+    fn baseline() -> State {
+        let ship = Ship {
+            object: object(2, 1000., [8000., 0.], [0., 2.]),
+            ship_type: ShipType::HAB(HabData {
+                thrust: 10000.,
+                dry_mass: 600.,
+                fuel_mass: 400.,
+                isp: 300.,
+                radius: 10.,
+                drag_coefficient: 0.8,
+                cross_section: 30.,
+                max_angular_acceleration: 0.1,
+            }),
+            landed: None,
+        };
+        let planet = Planet {
+            object: object(1, 5e14, [0., 0.], [0., 0.]),
+            radius: 6371.,
+            atmosphere: None,
+        };
+
+        State {
+            ships: vec![ship],
+            planets: vec![planet],
+            inputs: HashMap::new(),
+            settings: Default::default(),
+            clock: Clock::default(),
+        }
+    }
+
+    fn assert_quantized(actual: f64, expected: f64, quantum: f64) {
+        assert_eq!(
+            quantize(actual, quantum),
+            quantize(expected, quantum),
+            "{} is not {} to within {}",
+            actual,
+            expected,
+            quantum
+        );
+    }
+
+    fn assert_same_object(actual: &Object, expected: &Object) {
+        for i in 0..2 {
+            assert_quantized(actual.position[i], expected.position[i], POSITION_QUANTUM);
+            assert_quantized(actual.velocity[i], expected.velocity[i], VELOCITY_QUANTUM);
+            assert_quantized(actual.acceleration[i], expected.acceleration[i], ACCELERATION_QUANTUM);
+        }
+        assert_quantized(actual.heading, expected.heading, HEADING_QUANTUM);
+        assert_quantized(actual.spin, expected.spin, SPIN_QUANTUM);
+        assert_quantized(actual.mass, expected.mass, MASS_QUANTUM);
+    }
+
+    #[test]
+    fn applied_delta_rebuilds_the_quantized_state() {
+        let baseline = baseline();
+
+        let mut state = baseline.clone();
+        let ship = &mut state.ships[0];
+        ship.object.position = array![7999.123456, 12.345678];
+        ship.object.velocity = array![-0.0123456, 1.9987654];
+        ship.object.acceleration = array![-0.5214321, 0.0012345];
+        ship.object.heading = 1.2345678;
+        ship.object.spin = 0.01234567;
+        ship.set_fuel_mass(398.765);
+        ship.object.mass = ship.mass();
+        ship.landed = Some(Landing {
+            planet: 1,
+            angle: 0.5,
+        });
+        state.planets[0].object.heading = 0.0001234;
+        state.inputs.insert(
+            2,
+            Inputs {
+                navmode: NavMode::PROG,
+                throttle: 0.5,
+                reference: Some(1),
+                target: None,
+            },
+        );
+        state.clock.time = 12.5;
+        state.clock.step = 25;
+
+        let delta = StateDelta::between(&baseline, &state).unwrap();
+        let mut encoder = BincodeEncoder::new();
+        let delta: StateDelta = BincodeEncoder::decode(encoder.encode(&delta)).unwrap();
+        let applied = delta.apply(&baseline).unwrap();
+
+        assert_same_object(&applied.ships[0].object, &state.ships[0].object);
+        assert_quantized(applied.ships[0].fuel_mass(), state.ships[0].fuel_mass(), MASS_QUANTUM);
+        assert_eq!(applied.ships[0].landed.as_ref().map(|l| l.planet), Some(1));
+        assert_same_object(&applied.planets[0].object, &state.planets[0].object);
+        assert_eq!(applied.inputs, state.inputs);
+        assert_eq!(applied.clock.step, state.clock.step);
+
+        // A client chaining deltas from rebuilt baselines stays in step with the server.
+        let mut next = state.clone();
+        next.ships[0].object.position[0] += 0.0104;
+        next.ships[0].landed = None;
+        let delta = StateDelta::between(&state, &next).unwrap();
+        let rebuilt = delta.apply(&applied).unwrap();
+        assert_same_object(&rebuilt.ships[0].object, &next.ships[0].object);
+        assert!(rebuilt.ships[0].landed.is_none());
+    }
+
+    #[test]
+    fn added_bodies_call_for_a_keyframe() {
+        let baseline = baseline();
+        let mut state = baseline.clone();
+        state.planets.push(state.planets[0].clone());
+        state.planets[1].object.id = 3;
+
+        assert!(StateDelta::between(&baseline, &state).is_none());
+    }
+}
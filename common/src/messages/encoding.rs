@@ -1,9 +1,32 @@
+use bincode::Options;
 use serde::{Serialize, Deserialize};
 
+/// The bincode options used for every message.
+/// Integers are varint encoded, so the small quantized numbers in deltas take a byte or two.
+pub fn options() -> impl Options {
+    bincode::DefaultOptions::new().allow_trailing_bytes()
+}
+
+/// The bincode options of the handshake: `Hello`, `Welcome` and `Rejected`.
+/// Integers have a fixed size, as in the first version of the protocol,
+/// so builds of any version can read the version and the reason of a rejection.
+/// Never change them, even when `options` change.
+pub fn handshake_options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+}
+
 pub struct BincodeEncoder {
     output_buffer: Vec<u8>,
 }
 
+impl Default for BincodeEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl BincodeEncoder {
     pub fn new() -> BincodeEncoder {
         BincodeEncoder {
@@ -13,11 +36,52 @@ impl BincodeEncoder {
 
     pub fn encode<M: Serialize>(&mut self, message: M) -> &[u8] {
         self.output_buffer.clear();
-        bincode::serialize_into(&mut self.output_buffer, &message).unwrap();
+        options().serialize_into(&mut self.output_buffer, &message).unwrap();
         &self.output_buffer
     }
 
     pub fn decode<'a, M: Deserialize<'a>>(message_bin: &'a [u8]) -> Option<M> {
-        bincode::deserialize::<M>(message_bin).ok()
+        options().deserialize::<M>(message_bin).ok()
+    }
+
+    /// Encodes a handshake message with `handshake_options`.
+    pub fn encode_handshake<M: Serialize>(&mut self, message: M) -> &[u8] {
+        self.output_buffer.clear();
+        handshake_options().serialize_into(&mut self.output_buffer, &message).unwrap();
+        &self.output_buffer
+    }
+
+    /// Decodes a handshake message encoded with `handshake_options`.
+    pub fn decode_handshake<'a, M: Deserialize<'a>>(message_bin: &'a [u8]) -> Option<M> {
+        handshake_options().deserialize::<M>(message_bin).ok()
+    }
+}
+
+/// Encodes messages as JSON text, for clients that can't decode bincode, such as browsers.
+pub struct JsonEncoder {
+    output_buffer: Vec<u8>,
+}
+
+impl Default for JsonEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JsonEncoder {
+    pub fn new() -> JsonEncoder {
+        JsonEncoder {
+            output_buffer: Vec::new(),
+        }
+    }
+
+    pub fn encode<M: Serialize>(&mut self, message: M) -> &[u8] {
+        self.output_buffer.clear();
+        serde_json::to_writer(&mut self.output_buffer, &message).unwrap();
+        &self.output_buffer
+    }
+
+    pub fn decode<'a, M: Deserialize<'a>>(message_json: &'a [u8]) -> Option<M> {
+        serde_json::from_slice::<M>(message_json).ok()
     }
 }
@@ -1,9 +1,39 @@
 use serde::{Serialize, Deserialize};
 
 use crate::data::inputs::{NavMode};
+use crate::messages::handshake::Hello;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum FromClientMessage {
-    NavMode(NavMode),
-    Throttle(f32),
+    /// Opens the session. Nothing else is accepted before it.
+    /// This must stay the first variant, see `Hello`.
+    Hello(Hello),
+
+    /// Asks to take control of a ship.
+    /// Commands are only accepted for ships the client controls.
+    Claim(i32),
+
+    /// Gives up control of a ship.
+    Release(i32),
+
+    NavMode { ship: i32, navmode: NavMode },
+    Throttle { ship: i32, throttle: f32 },
+
+    /// Sets the body nav modes are relative to. `None` picks the strongest pull.
+    Reference { ship: i32, reference: Option<i32> },
+
+    /// Sets the body targeted by the target nav modes.
+    Target { ship: i32, target: Option<i32> },
+
+    /// Sent over UDP, to have the snapshots of a session streamed to the endpoint it comes from.
+    /// Carries the secret token of the session from `Welcome`, so others can't take its stream.
+    /// Repeated until snapshots arrive, as it may be lost.
+    Stream { token: u64 },
+
+    /// Tells the server the client has the snapshot of this tick,
+    /// so it can be used as the baseline of the next deltas.
+    Ack(u64),
+
+    /// Requests a time warp level, as an index in `WARP_LEVELS`.
+    Warp(usize),
 }
@@ -1,8 +1,37 @@
 use serde::{Serialize, Deserialize};
 
+use crate::data::event::Event;
 use crate::data::state::State;
+use crate::messages::delta::StateDelta;
+use crate::messages::handshake::Welcome;
 
 #[derive(Serialize, Deserialize)]
 pub enum FromServerMessage {
-    Update(State),
+    /// The handshake succeeded. Updates follow.
+    /// This must stay the first variant, see `Welcome`.
+    Welcome(Welcome),
+
+    /// The handshake failed, with the reason why. The server closes the connection.
+    /// This must stay the second variant, see `peek_rejection`.
+    Rejected(String),
+
+    /// A complete state. Sent to new clients, periodically, and whenever a delta can't be used.
+    /// Like deltas, it carries the simulation time in its clock, which clients use
+    /// to interpolate between snapshots.
+    Keyframe { tick: u64, state: State },
+
+    /// The state at `tick`, as a delta from the state at `baseline`, which the client acked.
+    Delta {
+        tick: u64,
+        baseline: u64,
+        delta: StateDelta,
+    },
+
+    Event(Event),
+
+    /// The client now controls this ship.
+    Claimed(i32),
+
+    /// A claim or command was refused, with the reason why.
+    Refused(String),
 }
\ No newline at end of file
@@ -0,0 +1,125 @@
+use std::{fmt, str::FromStr};
+
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+
+use super::encoding::handshake_options;
+
+/// Version of the protocol spoken between flight and the server.
+/// Bump it whenever a message changes in a way older builds can't decode.
+pub const PROTOCOL_VERSION: u32 = 5;
+
+/// What a client wants to do once connected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Role {
+    /// Can claim ships and fly them.
+    #[default]
+    Pilot,
+
+    /// Only receives updates.
+    Observer,
+
+    /// Can fly any ship, even one claimed by somebody else.
+    Admin,
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pilot" => Ok(Role::Pilot),
+            "observer" => Ok(Role::Observer),
+            "admin" => Ok(Role::Admin),
+            _ => Err(format!("unknown role '{s}', expected one of: pilot, observer, admin")),
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Role::Pilot => "pilot",
+            Role::Observer => "observer",
+            Role::Admin => "admin",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// The first message a client sends after connecting.
+///
+/// `Hello` is the first variant of `FromClientMessage`, and `version` is its first field.
+/// Keep it that way, and encode it with `handshake_options`, so `peek_version` can read the version of any build.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hello {
+    pub version: u32,
+    pub name: String,
+    pub role: Role,
+
+    /// The token of a previous session to resume, keeping the ships it controlled.
+    pub resume: Option<u64>,
+}
+
+/// The server's answer to an accepted `Hello`.
+///
+/// Like `Hello`, this is the first variant of `FromServerMessage` and starts with the version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Welcome {
+    pub version: u32,
+
+    /// Identifies this connection on the server.
+    pub session: u64,
+
+    /// A secret to give back in `Hello::resume` after losing the connection.
+    pub token: u64,
+    pub role: Role,
+}
+
+/// Reads the protocol version out of an encoded `Hello` or `Welcome`,
+/// even when the rest of the message can't be decoded by this build.
+pub fn peek_version(message_bin: &[u8]) -> Option<u32> {
+    let (variant, version): (u32, u32) = handshake_options().deserialize(message_bin).ok()?;
+    (variant == 0).then_some(version)
+}
+
+/// Reads the reason out of an encoded `FromServerMessage::Rejected`,
+/// which is the second variant in every version of the protocol.
+pub fn peek_rejection(message_bin: &[u8]) -> Option<String> {
+    let (variant, reason): (u32, String) = handshake_options().deserialize(message_bin).ok()?;
+    (variant == 1).then_some(reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::{encoding::BincodeEncoder, from_client::FromClientMessage, from_server::FromServerMessage};
+
+    #[test]
+    fn version_and_rejection_can_be_peeked() {
+        let mut encoder = BincodeEncoder::new();
+        let hello = encoder.encode_handshake(FromClientMessage::Hello(Hello {
+            version: PROTOCOL_VERSION,
+            name: "Ada".to_owned(),
+            role: Role::Pilot,
+            resume: None,
+        }));
+        assert_eq!(peek_version(hello), Some(PROTOCOL_VERSION));
+
+        let rejected = encoder.encode_handshake(FromServerMessage::Rejected("No".to_owned()));
+        assert_eq!(peek_rejection(rejected).as_deref(), Some("No"));
+    }
+
+    #[test]
+    fn the_header_is_fixed_size() {
+        // Variant 0 then the version, as little endian u32, as in the first version of the protocol.
+        let mut encoder = BincodeEncoder::new();
+        let hello = encoder.encode_handshake(FromClientMessage::Hello(Hello {
+            version: 7,
+            name: String::new(),
+            role: Role::Observer,
+            resume: Some(1),
+        }));
+        assert_eq!(&hello[..8], &[0, 0, 0, 0, 7, 0, 0, 0]);
+    }
+}
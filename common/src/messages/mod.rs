@@ -1,3 +1,6 @@
 pub mod from_client;
 pub mod from_server;
+pub mod delta;
 pub mod encoding;
+pub mod handshake;
+pub mod transport;
@@ -0,0 +1,39 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// How snapshots travel between the server and clients.
+///
+/// The handshake, commands and events always go over TCP, which is reliable.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransportKind {
+    /// Everything goes over TCP.
+    #[default]
+    Tcp,
+
+    /// Snapshots are streamed over UDP, on the same port as TCP.
+    /// A lost snapshot is simply skipped, instead of holding back the ones after it.
+    Udp,
+}
+
+impl FromStr for TransportKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tcp" => Ok(TransportKind::Tcp),
+            "udp" => Ok(TransportKind::Udp),
+            _ => Err(format!("unknown transport '{s}', expected one of: tcp, udp")),
+        }
+    }
+}
+
+impl fmt::Display for TransportKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            TransportKind::Tcp => "tcp",
+            TransportKind::Udp => "udp",
+        };
+        write!(f, "{name}")
+    }
+}
@@ -0,0 +1,285 @@
+use std::f64::consts::PI;
+
+use super::{cross, dot, norm, scale, sub, wrap_angle, Vector};
+
+/// Below this eccentricity, an orbit is treated as circular,
+/// and its periapsis is put at the current position.
+const CIRCULAR: f64 = 1e-9;
+
+/// Below this ratio of the angular momentum to `r v`, the sine of the angle between
+/// position and velocity, the motion is treated as radial, which no conic describes.
+const RADIAL: f64 = 1e-9;
+
+/// The shape of an orbit around a body, and where on it the orbiting body is.
+///
+/// Ellipses, parabolas and hyperbolas are all described, following the usual conventions:
+/// the semi-major axis is negative for hyperbolas, and infinite for parabolas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Elements {
+    /// The standard gravitational parameter of the central body, in m³ s⁻².
+    pub mu: f64,
+
+    /// Specific angular momentum, in m² s⁻¹.
+    /// Positive for counter-clockwise orbits, negative for clockwise ones.
+    pub angular_momentum: f64,
+
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+
+    /// The direction of the periapsis, from the central body.
+    pub argument_of_periapsis: f64,
+
+    /// The angle from the periapsis to the current position, in the direction of motion.
+    pub true_anomaly: f64,
+}
+
+impl Elements {
+    /// Computes the elements of a body at `position`, moving at `velocity`,
+    /// both relative to a central body with gravitational parameter `mu`.
+    ///
+    /// Returns `None` for degenerate cases: the body at the center, moving straight towards
+    /// or away from it, or at rest, or `mu` not positive.
+    pub fn from_state_vectors(mu: f64, position: Vector, velocity: Vector) -> Option<Elements> {
+        let r = norm(position);
+        if r <= 0. || mu <= 0. {
+            return None;
+        }
+
+        let h = cross(position, velocity);
+        let v2 = dot(velocity, velocity);
+        if h.abs() <= RADIAL * r * v2.sqrt() {
+            return None;
+        }
+
+        // e = ((v² - μ/r) r - (r·v) v) / μ
+        let e_vector = scale(
+            sub(
+                scale(position, v2 - mu / r),
+                scale(velocity, dot(position, velocity)),
+            ),
+            1. / mu,
+        );
+        let eccentricity = norm(e_vector);
+
+        let direction = if h < 0. { -1. } else { 1. };
+        let position_angle = position[1].atan2(position[0]);
+        let (argument_of_periapsis, true_anomaly) = if eccentricity < CIRCULAR {
+            (position_angle, 0.)
+        } else {
+            let argument_of_periapsis = e_vector[1].atan2(e_vector[0]);
+            let true_anomaly = wrap_angle(direction * (position_angle - argument_of_periapsis));
+            (argument_of_periapsis, true_anomaly)
+        };
+
+        // From the vis-viva equation.
+        let semi_major_axis = 1. / (2. / r - v2 / mu);
+
+        Some(Elements {
+            mu,
+            angular_momentum: h,
+            semi_major_axis,
+            eccentricity,
+            argument_of_periapsis,
+            true_anomaly,
+        })
+    }
+
+    /// Whether the orbit is closed, that is an ellipse.
+    pub fn is_bound(&self) -> bool {
+        self.eccentricity < 1.
+    }
+
+    /// +1 for counter-clockwise orbits, -1 for clockwise ones.
+    pub fn direction(&self) -> f64 {
+        if self.angular_momentum < 0. {
+            -1.
+        } else {
+            1.
+        }
+    }
+
+    /// The semi-latus rectum, h² / μ, in meters.
+    /// Unlike the semi-major axis, it stays finite for every conic.
+    pub fn semi_latus_rectum(&self) -> f64 {
+        self.angular_momentum.powi(2) / self.mu
+    }
+
+    /// The distance of the periapsis from the center of the central body.
+    pub fn periapsis(&self) -> f64 {
+        self.semi_latus_rectum() / (1. + self.eccentricity)
+    }
+
+    /// The distance of the apoapsis from the center of the central body,
+    /// or `None` if the orbit is open.
+    pub fn apoapsis(&self) -> Option<f64> {
+        self.is_bound()
+            .then(|| self.semi_latus_rectum() / (1. - self.eccentricity))
+    }
+
+    /// The time it takes to go around once, in seconds, or `None` if the orbit is open.
+    pub fn period(&self) -> Option<f64> {
+        self.is_bound()
+            .then(|| 2. * PI * (self.semi_major_axis.powi(3) / self.mu).sqrt())
+    }
+
+    /// The largest true anomaly the body can reach.
+    /// `PI` for ellipses, and the asymptote's angle for open orbits.
+    pub fn max_true_anomaly(&self) -> f64 {
+        if self.is_bound() {
+            PI
+        } else {
+            (-1. / self.eccentricity).acos()
+        }
+    }
+
+    /// The distance from the center of the central body at a true anomaly.
+    /// Infinite past the asymptotes of open orbits.
+    pub fn radius_at(&self, true_anomaly: f64) -> f64 {
+        let denominator = 1. + self.eccentricity * true_anomaly.cos();
+        if denominator <= 0. {
+            return f64::INFINITY;
+        }
+
+        self.semi_latus_rectum() / denominator
+    }
+
+    /// The position relative to the central body at a true anomaly.
+    pub fn position_at(&self, true_anomaly: f64) -> Vector {
+        let radius = self.radius_at(true_anomaly);
+        let angle = self.argument_of_periapsis + self.direction() * true_anomaly;
+        [radius * angle.cos(), radius * angle.sin()]
+    }
+
+    /// The velocity relative to the central body at a true anomaly.
+    pub fn velocity_at(&self, true_anomaly: f64) -> Vector {
+        let angle = self.argument_of_periapsis + self.direction() * true_anomaly;
+        let speed = self.mu / self.angular_momentum.abs();
+        let radial = speed * self.eccentricity * true_anomaly.sin();
+        let transverse = speed * (1. + self.eccentricity * true_anomaly.cos());
+
+        // The transverse direction is a quarter turn ahead of the radial one,
+        // in the direction of motion.
+        let direction = self.direction();
+        [
+            radial * angle.cos() - direction * transverse * angle.sin(),
+            radial * angle.sin() + direction * transverse * angle.cos(),
+        ]
+    }
+
+    /// The position and velocity relative to the central body, the inverse of
+    /// `from_state_vectors`.
+    pub fn state_vectors(&self) -> (Vector, Vector) {
+        (
+            self.position_at(self.true_anomaly),
+            self.velocity_at(self.true_anomaly),
+        )
+    }
+}
+
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// The gravitational parameter of the Earth.
+    pub const MU: f64 = 3.986004418e14;
+
+    /// The state of a body at `radius`, at `angle` from +x, moving at `speed` times the
+    /// escape speed, with `flight_path` the angle of the velocity above the local horizontal.
+    /// `direction` is +1 for counter-clockwise motion, and -1 for clockwise motion.
+    pub fn state(radius: f64, angle: f64, speed: f64, flight_path: f64, direction: f64) -> (Vector, Vector) {
+        let speed = speed * (2. * MU / radius).sqrt();
+        let outward = [angle.cos(), angle.sin()];
+        let forward = [-direction * angle.sin(), direction * angle.cos()];
+        let velocity = [
+            speed * (flight_path.sin() * outward[0] + flight_path.cos() * forward[0]),
+            speed * (flight_path.sin() * outward[1] + flight_path.cos() * forward[1]),
+        ];
+        (scale(outward, radius), velocity)
+    }
+
+    /// Asserts that two states are the same, relative to the size of the first.
+    pub fn assert_same_state(expected: (Vector, Vector), actual: (Vector, Vector), tolerance: f64) {
+        let position_error = norm(sub(expected.0, actual.0)) / norm(expected.0);
+        let velocity_error = norm(sub(expected.1, actual.1)) / norm(expected.1);
+        assert!(
+            position_error < tolerance && velocity_error < tolerance,
+            "expected {:?}, got {:?}: errors {:e} and {:e}",
+            expected,
+            actual,
+            position_error,
+            velocity_error
+        );
+    }
+
+    fn round_trip(state: (Vector, Vector)) {
+        let elements = Elements::from_state_vectors(MU, state.0, state.1).unwrap();
+        assert_same_state(state, elements.state_vectors(), 1e-9);
+    }
+
+    proptest! {
+        #[test]
+        fn elliptic_states_round_trip(
+            radius in 6.4e6..4e8,
+            angle in -PI..PI,
+            speed in 0.05..0.99,
+            flight_path in -1.5..1.5,
+        ) {
+            round_trip(state(radius, angle, speed, flight_path, 1.));
+        }
+
+        #[test]
+        fn hyperbolic_states_round_trip(
+            radius in 6.4e6..4e8,
+            angle in -PI..PI,
+            speed in 1.01..5.,
+            flight_path in -1.5..1.5,
+        ) {
+            round_trip(state(radius, angle, speed, flight_path, 1.));
+        }
+
+        #[test]
+        fn clockwise_states_round_trip(
+            radius in 6.4e6..4e8,
+            angle in -PI..PI,
+            speed in 0.05..5.,
+            flight_path in -1.5..1.5,
+        ) {
+            let (position, velocity) = state(radius, angle, speed, flight_path, -1.);
+            let elements = Elements::from_state_vectors(MU, position, velocity).unwrap();
+            prop_assert_eq!(elements.direction(), -1.);
+            round_trip((position, velocity));
+        }
+
+        #[test]
+        fn near_parabolic_states_round_trip(
+            radius in 6.4e6..4e8,
+            angle in -PI..PI,
+            offset in -1e-6..1e-6,
+            flight_path in -1.5..1.5,
+        ) {
+            round_trip(state(radius, angle, 1. + offset, flight_path, 1.));
+        }
+    }
+
+    #[test]
+    fn circular_orbit() {
+        let (position, velocity) = state(7e6, 1., 0.5f64.sqrt(), 0., 1.);
+        let elements = Elements::from_state_vectors(MU, position, velocity).unwrap();
+
+        assert!(elements.eccentricity < 1e-12);
+        assert!((elements.semi_major_axis - 7e6).abs() < 1e-3);
+        assert!((elements.period().unwrap() - 2. * PI * (7e6f64.powi(3) / MU).sqrt()).abs() < 1e-6);
+        round_trip((position, velocity));
+    }
+
+    #[test]
+    fn radial_motion_has_no_elements() {
+        assert!(Elements::from_state_vectors(MU, [7e6, 0.], [0., 0.]).is_none());
+        assert!(Elements::from_state_vectors(MU, [7e6, 0.], [3e3, 0.]).is_none());
+        assert!(Elements::from_state_vectors(MU, [7e6, 7e6], [-1e4, -1e4]).is_none());
+        assert!(Elements::from_state_vectors(MU, [0., 0.], [0., 7e3]).is_none());
+    }
+}
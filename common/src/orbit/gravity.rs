@@ -0,0 +1,46 @@
+use crate::data::object::Planet;
+
+use super::{dot, norm, position_of, sub, Vector};
+
+/// The gravitational constant, in m³ kg⁻¹ s⁻².
+pub const G: f64 = 6.674e-11;
+
+/// The standard gravitational parameter of a body, G times its mass, in m³ s⁻².
+pub fn gravitational_parameter(mass: f64) -> f64 {
+    G * mass
+}
+
+/// The gravitational acceleration at `position`, from every planet.
+/// Ships are left out, as they are test particles unless the server says otherwise.
+///
+/// This is the server's model without softening, which only matters inside planets.
+pub fn acceleration(position: Vector, planets: &[Planet]) -> Vector {
+    let mut acceleration = [0., 0.];
+
+    for planet in planets {
+        let offset = sub(position_of(&planet.object), position);
+        let distance = norm(offset);
+        if distance <= 0. {
+            continue;
+        }
+
+        let magnitude = gravitational_parameter(planet.object.mass) / distance.powi(3);
+        acceleration[0] += offset[0] * magnitude;
+        acceleration[1] += offset[1] * magnitude;
+    }
+
+    acceleration
+}
+
+/// The planet with the strongest pull at `position`.
+/// This is the reference body ships use when none is set.
+pub fn dominant_planet(position: Vector, planets: &[Planet]) -> Option<&Planet> {
+    planets
+        .iter()
+        .map(|planet| {
+            let offset = sub(position_of(&planet.object), position);
+            (planet, planet.object.mass / dot(offset, offset))
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(planet, _)| planet)
+}
@@ -0,0 +1,471 @@
+//! Where a body is on its conic at a given time, from Kepler's equation.
+//!
+//! The elliptic and hyperbolic forms of the equation lose precision as the eccentricity
+//! gets close to one, so propagation goes through the universal form instead,
+//! which holds for every conic and everything in between.
+
+use std::f64::consts::PI;
+
+use super::{add, dot, elements::Elements, norm, scale, wrap_angle, Vector};
+
+/// Anomalies are solved to this precision, in radians.
+const TOLERANCE: f64 = 1e-12;
+
+/// Below this magnitude of `z`, the Stumpff functions are computed from their series,
+/// as the closed forms cancel out.
+const STUMPFF_SERIES: f64 = 1e-2;
+
+/// Newton's method converges in a handful of iterations from the starting points used,
+/// this is only a safeguard.
+const MAX_ITERATIONS: usize = 50;
+
+/// Solves Kepler's equation `M = E - e sin E` for the eccentric anomaly `E`, with `e < 1`.
+pub fn solve_elliptic(mean_anomaly: f64, eccentricity: f64) -> f64 {
+    let mean_anomaly = wrap_angle(mean_anomaly);
+    let mut anomaly = if eccentricity > 0.8 {
+        PI.copysign(mean_anomaly)
+    } else {
+        mean_anomaly
+    };
+
+    for _ in 0..MAX_ITERATIONS {
+        let step = (anomaly - eccentricity * anomaly.sin() - mean_anomaly)
+            / (1. - eccentricity * anomaly.cos());
+        anomaly -= step;
+        if step.abs() < TOLERANCE {
+            break;
+        }
+    }
+
+    anomaly
+}
+
+/// Solves the hyperbolic Kepler equation `M = e sinh H - H` for the hyperbolic anomaly `H`,
+/// with `e > 1`.
+pub fn solve_hyperbolic(mean_anomaly: f64, eccentricity: f64) -> f64 {
+    // Close to the solution for large anomalies, where sinh dominates.
+    let mut anomaly = (2. * mean_anomaly.abs() / eccentricity + 1.8)
+        .ln()
+        .copysign(mean_anomaly);
+
+    for _ in 0..MAX_ITERATIONS {
+        let step = (eccentricity * anomaly.sinh() - anomaly - mean_anomaly)
+            / (eccentricity * anomaly.cosh() - 1.);
+        anomaly -= step;
+        if step.abs() < TOLERANCE * anomaly.abs().max(1.) {
+            break;
+        }
+    }
+
+    anomaly
+}
+
+/// Solves Barker's equation `M = D + D³ / 3` for `D = tan(ν / 2)`, on parabolic orbits.
+pub fn solve_parabolic(mean_anomaly: f64) -> f64 {
+    // Cardano's formula, for the only real root.
+    let w = (1.5 * mean_anomaly + (1. + (1.5 * mean_anomaly).powi(2)).sqrt()).cbrt();
+    w - 1. / w
+}
+
+pub fn eccentric_from_true(true_anomaly: f64, eccentricity: f64) -> f64 {
+    2. * (((1. - eccentricity) / (1. + eccentricity)).sqrt() * (true_anomaly / 2.).tan()).atan()
+}
+
+pub fn true_from_eccentric(eccentric_anomaly: f64, eccentricity: f64) -> f64 {
+    2. * (((1. + eccentricity) / (1. - eccentricity)).sqrt() * (eccentric_anomaly / 2.).tan())
+        .atan()
+}
+
+pub fn hyperbolic_from_true(true_anomaly: f64, eccentricity: f64) -> f64 {
+    2. * (((eccentricity - 1.) / (eccentricity + 1.)).sqrt() * (true_anomaly / 2.).tan()).atanh()
+}
+
+pub fn true_from_hyperbolic(hyperbolic_anomaly: f64, eccentricity: f64) -> f64 {
+    2. * (((eccentricity + 1.) / (eccentricity - 1.)).sqrt() * (hyperbolic_anomaly / 2.).tanh())
+        .atan()
+}
+
+/// The Stumpff function `C(z) = (1 - cos √z) / z`, extended to negative `z` with `cosh`.
+pub fn stumpff_c(z: f64) -> f64 {
+    if z.abs() < STUMPFF_SERIES {
+        1. / 2. - z / 24. + z.powi(2) / 720. - z.powi(3) / 40_320. + z.powi(4) / 3_628_800.
+    } else if z > 0. {
+        (1. - z.sqrt().cos()) / z
+    } else {
+        ((-z).sqrt().cosh() - 1.) / -z
+    }
+}
+
+/// The Stumpff function `S(z) = (√z - sin √z) / √z³`, extended to negative `z` with `sinh`.
+pub fn stumpff_s(z: f64) -> f64 {
+    if z.abs() < STUMPFF_SERIES {
+        1. / 6. - z / 120. + z.powi(2) / 5_040. - z.powi(3) / 362_880. + z.powi(4) / 39_916_800.
+    } else if z > 0. {
+        let root = z.sqrt();
+        (root - root.sin()) / root.powi(3)
+    } else {
+        let root = (-z).sqrt();
+        (root.sinh() - root) / root.powi(3)
+    }
+}
+
+/// Moves a body at `position` and `velocity` around a central body with gravitational
+/// parameter `mu` for `dt` seconds, with Kepler's equation in universal variables.
+/// Returns the new position and velocity.
+///
+/// The equation is increasing in the universal anomaly, so it is solved with Newton's method,
+/// falling back to bisection whenever a step would leave the bracket around the root,
+/// or converge too slowly.
+fn universal(mu: f64, position: Vector, velocity: Vector, dt: f64) -> (Vector, Vector) {
+    let r0 = norm(position);
+    let sqrt_mu = mu.sqrt();
+    let radial = dot(position, velocity) / sqrt_mu;
+    let alpha = 2. / r0 - dot(velocity, velocity) / mu;
+
+    // Whole turns of an ellipse change nothing, and would make the anomaly needlessly large.
+    let dt = if alpha > 0. {
+        let period = 2. * PI / (alpha.powi(3) * mu).sqrt();
+        dt - (dt / period).round() * period
+    } else {
+        dt
+    };
+
+    // The time it takes to reach an anomaly of `chi`, times √μ, less √μ dt,
+    // and its derivative, the distance at `chi`.
+    let kepler = |chi: f64| {
+        let z = alpha * chi * chi;
+        let (c, s) = (stumpff_c(z), stumpff_s(z));
+        let value = radial * chi * chi * c + (1. - alpha * r0) * chi.powi(3) * s + r0 * chi
+            - sqrt_mu * dt;
+        let distance = radial * chi * (1. - z * s) + (1. - alpha * r0) * chi * chi * c + r0;
+        (value, distance)
+    };
+
+    // The distance is never below the periapsis, so the anomaly is at most √μ |dt| / r_p.
+    let periapsis = {
+        let h = position[0] * velocity[1] - position[1] * velocity[0];
+        let p = h * h / mu;
+        let e = (1. - alpha * p).max(0.).sqrt();
+        p / (1. + e)
+    };
+    let bound = sqrt_mu * dt / periapsis;
+    let (mut low, mut high) = if dt >= 0. { (0., bound) } else { (bound, 0.) };
+
+    let mut chi = if alpha > 0. { sqrt_mu * alpha * dt } else { sqrt_mu * dt / r0 };
+    let mut last_step = high - low;
+    for _ in 0..MAX_ITERATIONS * 4 {
+        let (value, distance) = kepler(chi);
+
+        // The Stumpff functions overflow on hyperbolas, far past the root.
+        let past = if value.is_nan() { dt > 0. } else { value > 0. };
+        if past {
+            high = chi;
+        } else {
+            low = chi;
+        }
+
+        // Far out on a hyperbola, Newton's steps crawl down the exponential,
+        // so they are only taken when they at least halve the last one.
+        let newton = chi - value / distance;
+        let next = if newton > low && newton < high && (value / distance).abs() < last_step.abs() / 2.
+        {
+            newton
+        } else {
+            (low + high) / 2.
+        };
+        last_step = next - chi;
+
+        let converged = (next - chi).abs() <= TOLERANCE * chi.abs().max(1.);
+        chi = next;
+        if converged || low == high {
+            break;
+        }
+    }
+
+    let z = alpha * chi * chi;
+    let (c, s) = (stumpff_c(z), stumpff_s(z));
+
+    // Lagrange's coefficients.
+    let f = 1. - chi * chi / r0 * c;
+    let g = dt - chi.powi(3) / sqrt_mu * s;
+    let new_position = add(scale(position, f), scale(velocity, g));
+
+    let r = norm(new_position);
+    let f_dot = sqrt_mu / (r * r0) * (alpha * chi.powi(3) * s - chi);
+    let g_dot = 1. - chi * chi / r * c;
+    let new_velocity = add(scale(position, f_dot), scale(velocity, g_dot));
+
+    (new_position, new_velocity)
+}
+
+/// The kind of conic, as far as Kepler's equation is concerned.
+enum Conic {
+    Ellipse,
+    Parabola,
+    Hyperbola,
+}
+
+impl Elements {
+    fn conic(&self) -> Conic {
+        if self.eccentricity < 1. {
+            Conic::Ellipse
+        } else if self.eccentricity > 1. {
+            Conic::Hyperbola
+        } else {
+            Conic::Parabola
+        }
+    }
+
+    /// How fast the mean anomaly grows, in radians per second.
+    /// Close to an eccentricity of one, the mean anomaly loses precision:
+    /// `propagate` and `time_since_periapsis` don't depend on it.
+    ///
+    /// For parabolas, where the mean anomaly is Barker's `D + D³ / 3`, this is `2 √(μ / p³)`.
+    pub fn mean_motion(&self) -> f64 {
+        match self.conic() {
+            Conic::Ellipse => (self.mu / self.semi_major_axis.powi(3)).sqrt(),
+            Conic::Parabola => 2. * (self.mu / self.semi_latus_rectum().powi(3)).sqrt(),
+            Conic::Hyperbola => (self.mu / (-self.semi_major_axis).powi(3)).sqrt(),
+        }
+    }
+
+    /// The mean anomaly at a true anomaly. It grows linearly with time,
+    /// and is zero at the periapsis.
+    pub fn mean_anomaly_at(&self, true_anomaly: f64) -> f64 {
+        let e = self.eccentricity;
+        match self.conic() {
+            Conic::Ellipse => {
+                let anomaly = eccentric_from_true(true_anomaly, e);
+                anomaly - e * anomaly.sin()
+            }
+            Conic::Parabola => {
+                let d = (true_anomaly / 2.).tan();
+                d + d.powi(3) / 3.
+            }
+            Conic::Hyperbola => {
+                let anomaly = hyperbolic_from_true(true_anomaly, e);
+                e * anomaly.sinh() - anomaly
+            }
+        }
+    }
+
+    /// The true anomaly at a mean anomaly.
+    pub fn true_anomaly_at(&self, mean_anomaly: f64) -> f64 {
+        let e = self.eccentricity;
+        match self.conic() {
+            Conic::Ellipse => true_from_eccentric(solve_elliptic(mean_anomaly, e), e),
+            Conic::Parabola => 2. * solve_parabolic(mean_anomaly).atan(),
+            Conic::Hyperbola => true_from_hyperbolic(solve_hyperbolic(mean_anomaly, e), e),
+        }
+    }
+
+    /// The time since the last periapsis passage, in seconds.
+    /// Negative on open orbits before the periapsis, and on ellipses past the apoapsis.
+    pub fn time_since_periapsis(&self) -> f64 {
+        let e = self.eccentricity;
+        let p = self.semi_latus_rectum();
+        let periapsis = p / (1. + e);
+
+        // The universal anomaly at the current position, from tan(ν / 2).
+        // It is √a E on ellipses, √p D on parabolas and √-a H on hyperbolas.
+        let d = (self.true_anomaly / 2.).tan();
+        let q = (1. - e) / (1. + e) * d * d;
+        let ratio = if q == 0. {
+            1.
+        } else if q > 0. {
+            q.sqrt().atan() / q.sqrt()
+        } else {
+            (-q).sqrt().atanh() / (-q).sqrt()
+        };
+        let chi = 2. * p.sqrt() / (1. + e) * d * ratio;
+
+        // Kepler's equation in universal variables, from the periapsis.
+        let z = (1. - e * e) / p * chi * chi;
+        (e * chi.powi(3) * stumpff_s(z) + periapsis * chi) / self.mu.sqrt()
+    }
+
+    /// The time until the next periapsis passage, in seconds,
+    /// or `None` if an open orbit already passed it.
+    pub fn time_to_periapsis(&self) -> Option<f64> {
+        let since = self.time_since_periapsis();
+        match self.period() {
+            Some(period) => Some((-since).rem_euclid(period)),
+            None => (since <= 0.).then_some(-since),
+        }
+    }
+
+    /// The same orbit, `dt` seconds later. `dt` can be negative.
+    pub fn propagate(&self, dt: f64) -> Elements {
+        let (position, velocity) = self.state_vectors();
+        let (position, _) = universal(self.mu, position, velocity, dt);
+
+        // Only the position along the orbit changes, the shape is kept as it was.
+        let angle = position[1].atan2(position[0]);
+        Elements {
+            true_anomaly: wrap_angle(self.direction() * (angle - self.argument_of_periapsis)),
+            ..*self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::orbit::{
+        elements::tests::{assert_same_state, state, MU},
+        sub,
+    };
+
+    /// Integrates the two-body problem with RK4, for comparison.
+    fn integrate(position: Vector, velocity: Vector, dt: f64, steps: usize) -> (Vector, Vector) {
+        let acceleration = |r: Vector| scale(r, -MU / norm(r).powi(3));
+        let h = dt / steps as f64;
+        let (mut r, mut v) = (position, velocity);
+
+        for _ in 0..steps {
+            let (k1r, k1v) = (v, acceleration(r));
+            let (k2r, k2v) = (add(v, scale(k1v, h / 2.)), acceleration(add(r, scale(k1r, h / 2.))));
+            let (k3r, k3v) = (add(v, scale(k2v, h / 2.)), acceleration(add(r, scale(k2r, h / 2.))));
+            let (k4r, k4v) = (add(v, scale(k3v, h)), acceleration(add(r, scale(k3r, h))));
+
+            let weighted = |a: Vector, b: Vector, c: Vector, d: Vector| {
+                scale(add(add(a, scale(add(b, c), 2.)), d), h / 6.)
+            };
+            r = add(r, weighted(k1r, k2r, k3r, k4r));
+            v = add(v, weighted(k1v, k2v, k3v, k4v));
+        }
+
+        (r, v)
+    }
+
+    /// A time scale of the orbit at `position`: how long it takes to cover its distance
+    /// to the center at the circular speed.
+    fn time_scale(position: Vector) -> f64 {
+        (norm(position).powi(3) / MU).sqrt()
+    }
+
+    fn assert_returns(elements: Elements, dt: f64, tolerance: f64) {
+        let there = elements.propagate(dt);
+        let back = there.propagate(-dt);
+        assert_same_state(elements.state_vectors(), back.state_vectors(), tolerance);
+    }
+
+    proptest! {
+        #[test]
+        fn propagation_is_reversible(
+            radius in 6.4e6..4e8,
+            angle in -PI..PI,
+            speed in 0.05..5.,
+            flight_path in -1.3..1.3,
+            direction in prop_oneof![Just(1.), Just(-1.)],
+            time in -20.0..20.,
+        ) {
+            let (position, velocity) = state(radius, angle, speed, flight_path, direction);
+            let elements = Elements::from_state_vectors(MU, position, velocity).unwrap();
+            assert_returns(elements, time * time_scale(position), 1e-7);
+        }
+
+        #[test]
+        fn near_parabolic_propagation_is_reversible(
+            radius in 6.4e6..4e8,
+            angle in -PI..PI,
+            offset in -1e-6..1e-6,
+            flight_path in -1.57..1.57,
+            time in -20.0..20.,
+        ) {
+            let (position, velocity) = state(radius, angle, 1. + offset, flight_path, 1.);
+            let elements = Elements::from_state_vectors(MU, position, velocity).unwrap();
+
+            // Nearly radial orbits lose a few digits going through the true anomaly.
+            assert_returns(elements, time * time_scale(position), 1e-6);
+        }
+
+        #[test]
+        fn propagation_matches_integration(
+            radius in 6.4e6..4e7,
+            angle in -PI..PI,
+            speed in 0.4..2.,
+            flight_path in -0.8..0.8,
+            direction in prop_oneof![Just(1.), Just(-1.)],
+            time in 0.1..2.,
+        ) {
+            let (position, velocity) = state(radius, angle, speed, flight_path, direction);
+            let elements = Elements::from_state_vectors(MU, position, velocity).unwrap();
+            let dt = time * time_scale(position);
+
+            // Close passes need much smaller steps than the integrator takes.
+            prop_assume!(elements.periapsis() > radius / 4.);
+
+            let integrated = integrate(position, velocity, dt, 20_000);
+            assert_same_state(integrated, elements.propagate(dt).state_vectors(), 1e-7);
+        }
+
+        #[test]
+        fn time_since_periapsis_leads_back_to_it(
+            radius in 6.4e6..4e8,
+            angle in -PI..PI,
+            speed in 0.05..5.,
+            flight_path in -1.5..1.5,
+        ) {
+            let (position, velocity) = state(radius, angle, speed, flight_path, 1.);
+            let elements = Elements::from_state_vectors(MU, position, velocity).unwrap();
+            let periapsis = elements.propagate(-elements.time_since_periapsis());
+
+            let error = norm(sub(periapsis.state_vectors().0, elements.position_at(0.)));
+            prop_assert!(error < 1e-8 * radius, "missed by {:e} m", error);
+        }
+    }
+
+    /// Slightly open, and almost straight out, where the parabolic approximation used to fail.
+    #[test]
+    fn near_radial_near_parabolic_orbit() {
+        let flight_path = (1e-2f64).acos();
+        let (position, velocity) = state(7e6, 0.3, 1. + 1.75e-3, flight_path, 1.);
+        let elements = Elements::from_state_vectors(MU, position, velocity).unwrap();
+        assert!((elements.eccentricity - 1. - 7e-7).abs() < 1e-8);
+
+        for time in [-20., -1., 0.5, 3., 20.] {
+            let dt = time * time_scale(position);
+            // Nearly radial orbits lose a few digits going through the true anomaly.
+            assert_returns(elements, dt, 1e-6);
+        }
+
+        let dt = 2. * time_scale(position);
+        let integrated = integrate(position, velocity, dt, 200_000);
+        assert_same_state(integrated, elements.propagate(dt).state_vectors(), 1e-6);
+    }
+
+    #[test]
+    fn whole_periods_change_nothing() {
+        let (position, velocity) = state(7e6, 1., 0.6, 0.3, 1.);
+        let elements = Elements::from_state_vectors(MU, position, velocity).unwrap();
+        let period = elements.period().unwrap();
+
+        assert_same_state(
+            elements.state_vectors(),
+            elements.propagate(100. * period).state_vectors(),
+            1e-8,
+        );
+    }
+
+    #[test]
+    fn kepler_equation_is_solved() {
+        for eccentricity in [0., 0.1, 0.5, 0.9, 0.99] {
+            for mean_anomaly in [-3., -1., 0., 0.5, 2., 3.1] {
+                let anomaly = solve_elliptic(mean_anomaly, eccentricity);
+                let error = anomaly - eccentricity * anomaly.sin() - mean_anomaly;
+                assert!(error.abs() < 1e-10, "e = {}, M = {}", eccentricity, mean_anomaly);
+            }
+        }
+
+        for eccentricity in [1.01, 1.5, 3., 10.] {
+            for mean_anomaly in [-100., -1., 0., 0.5, 20., 1e4] {
+                let anomaly = solve_hyperbolic(mean_anomaly, eccentricity);
+                let error = eccentricity * anomaly.sinh() - anomaly - mean_anomaly;
+                assert!(error.abs() < 1e-9 * mean_anomaly.abs().max(1.), "e = {}, M = {}", eccentricity, mean_anomaly);
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Orbital mechanics in the plane, shared by the server and clients.
+//!
+//! Vectors are `[x, y]` arrays, in meters and seconds.
+//! Angles are in radians, counter-clockwise from +x.
+
+use std::f64::consts::PI;
+
+use crate::data::object::Object;
+
+pub mod elements;
+pub mod gravity;
+pub mod kepler;
+pub mod relative;
+pub mod transfer;
+
+/// A 2D vector.
+pub type Vector = [f64; 2];
+
+pub fn add(a: Vector, b: Vector) -> Vector {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+pub fn sub(a: Vector, b: Vector) -> Vector {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+pub fn scale(a: Vector, k: f64) -> Vector {
+    [a[0] * k, a[1] * k]
+}
+
+pub fn dot(a: Vector, b: Vector) -> f64 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+/// The z component of the 3D cross product. Positive when `b` is counter-clockwise of `a`.
+pub fn cross(a: Vector, b: Vector) -> f64 {
+    a[0] * b[1] - a[1] * b[0]
+}
+
+pub fn norm(a: Vector) -> f64 {
+    dot(a, a).sqrt()
+}
+
+/// Wraps an angle to [-PI, PI).
+pub fn wrap_angle(angle: f64) -> f64 {
+    (angle + PI).rem_euclid(2. * PI) - PI
+}
+
+pub fn position_of(object: &Object) -> Vector {
+    [object.position[0], object.position[1]]
+}
+
+pub fn velocity_of(object: &Object) -> Vector {
+    [object.velocity[0], object.velocity[1]]
+}
@@ -0,0 +1,43 @@
+use crate::data::object::Object;
+
+use super::{dot, elements::Elements, norm, position_of, sub, velocity_of, Vector};
+
+/// The motion of a body relative to a reference body.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Relative {
+    pub position: Vector,
+    pub velocity: Vector,
+}
+
+impl Relative {
+    pub fn between(object: &Object, reference: &Object) -> Relative {
+        Relative {
+            position: sub(position_of(object), position_of(reference)),
+            velocity: sub(velocity_of(object), velocity_of(reference)),
+        }
+    }
+
+    /// The distance between the centers of the two bodies.
+    pub fn distance(&self) -> f64 {
+        norm(self.position)
+    }
+
+    pub fn speed(&self) -> f64 {
+        norm(self.velocity)
+    }
+
+    /// The speed away from the reference. Negative when getting closer.
+    pub fn vertical_speed(&self) -> f64 {
+        let distance = self.distance();
+        if distance <= 0. {
+            return 0.;
+        }
+
+        dot(self.position, self.velocity) / distance
+    }
+
+    /// The orbit around a reference with gravitational parameter `mu`.
+    pub fn elements(&self, mu: f64) -> Option<Elements> {
+        Elements::from_state_vectors(mu, self.position, self.velocity)
+    }
+}
@@ -0,0 +1,216 @@
+//! Spheres of influence and transfers between circular orbits.
+
+use std::f64::consts::PI;
+
+use super::{cross, dot, elements::Elements, norm, sub, Vector};
+
+/// Number of samples the search for the closest approach starts from.
+const APPROACH_SAMPLES: usize = 512;
+
+/// Number of golden-section steps refining the closest approach.
+const APPROACH_REFINEMENT: usize = 40;
+
+/// The radius of the sphere of influence of a body of `mass`, orbiting a parent of
+/// `parent_mass` at `semi_major_axis`, following Laplace.
+/// Inside it, the body is the better reference for a two-body approximation.
+pub fn sphere_of_influence(semi_major_axis: f64, mass: f64, parent_mass: f64) -> f64 {
+    semi_major_axis * (mass / parent_mass).powf(0.4)
+}
+
+/// The signed angle from `from` to `to`, in (-PI, PI], positive counter-clockwise.
+/// Multiply by the orbit's direction to get the angle in the direction of motion.
+pub fn phase_angle(from: Vector, to: Vector) -> f64 {
+    cross(from, to).atan2(dot(from, to))
+}
+
+/// A Hohmann transfer between two coplanar circular orbits around the same body.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hohmann {
+    /// The change in speed at departure, in meters per second.
+    /// Negative when braking, for transfers to a lower orbit.
+    pub departure_delta_v: f64,
+
+    /// The change in speed on arrival, in meters per second.
+    pub arrival_delta_v: f64,
+
+    /// The time from departure to arrival, half a period of the transfer orbit, in seconds.
+    pub transfer_time: f64,
+
+    /// How far ahead of the ship, in the direction of motion, a target on the destination
+    /// orbit must be at departure to be met on arrival. Negative when it must be behind.
+    pub phase_angle: f64,
+}
+
+impl Hohmann {
+    /// Plans the transfer from a circular orbit of `from` radius to one of `to` radius,
+    /// around a body of gravitational parameter `mu`.
+    /// Returns `None` if either radius, or `mu`, isn't positive.
+    pub fn between(mu: f64, from: f64, to: f64) -> Option<Hohmann> {
+        if mu <= 0. || from <= 0. || to <= 0. {
+            return None;
+        }
+
+        let semi_major_axis = (from + to) / 2.;
+        let departure_delta_v = (mu / from).sqrt() * ((to / semi_major_axis).sqrt() - 1.);
+        let arrival_delta_v = (mu / to).sqrt() * (1. - (from / semi_major_axis).sqrt());
+        let transfer_time = PI * (semi_major_axis.powi(3) / mu).sqrt();
+
+        // The target covers this much of its orbit during the transfer,
+        // which must end half a turn from the departure point.
+        let target_motion = (mu / to.powi(3)).sqrt() * transfer_time;
+
+        Some(Hohmann {
+            departure_delta_v,
+            arrival_delta_v,
+            transfer_time,
+            phase_angle: PI - target_motion,
+        })
+    }
+
+    /// The total change in speed, in meters per second.
+    pub fn delta_v(&self) -> f64 {
+        self.departure_delta_v.abs() + self.arrival_delta_v.abs()
+    }
+}
+
+/// The time until the phase angle between two bodies on circular orbits reaches `required`,
+/// in seconds, or `None` if it never changes.
+///
+/// `current` and `required` are the angles from the first body to the second,
+/// and `mean_motions` how fast each goes around, in radians per second,
+/// all measured in the same direction.
+pub fn time_to_phase_angle(current: f64, required: f64, mean_motions: (f64, f64)) -> Option<f64> {
+    let relative_motion = mean_motions.1 - mean_motions.0;
+    if relative_motion == 0. {
+        return None;
+    }
+
+    let synodic_period = 2. * PI / relative_motion.abs();
+    Some(((required - current) / relative_motion).rem_euclid(synodic_period))
+}
+
+/// When two bodies on conics around the same body are closest, within `horizon` seconds
+/// from now, as the time from now and the distance between them.
+///
+/// The distance is sampled over the horizon, then refined around the closest sample,
+/// so approaches much shorter than `horizon / 512` can be missed.
+pub fn closest_approach(a: &Elements, b: &Elements, horizon: f64) -> (f64, f64) {
+    let distance = |time: f64| {
+        let (a, b) = (a.propagate(time), b.propagate(time));
+        norm(sub(a.position_at(a.true_anomaly), b.position_at(b.true_anomaly)))
+    };
+
+    let step = horizon / APPROACH_SAMPLES as f64;
+    let closest = (0..=APPROACH_SAMPLES)
+        .map(|i| i as f64 * step)
+        .map(|time| (time, distance(time)))
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .unwrap_or((0., distance(0.)));
+
+    // Golden-section search around the closest sample.
+    let ratio = (5f64.sqrt() - 1.) / 2.;
+    let (mut low, mut high) = ((closest.0 - step).max(0.), (closest.0 + step).min(horizon));
+    for _ in 0..APPROACH_REFINEMENT {
+        let left = high - ratio * (high - low);
+        let right = low + ratio * (high - low);
+        if distance(left) < distance(right) {
+            high = right;
+        } else {
+            low = left;
+        }
+    }
+
+    let time = (low + high) / 2.;
+    let refined = (time, distance(time));
+    if refined.1 < closest.1 {
+        refined
+    } else {
+        closest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MU_EARTH: f64 = 3.986004418e14;
+    const MU_SUN: f64 = 1.32712440018e20;
+    const DAY: f64 = 86_400.;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "expected {} ± {}, got {}",
+            expected,
+            tolerance,
+            actual
+        );
+    }
+
+    #[test]
+    fn leo_to_geo() {
+        let transfer = Hohmann::between(MU_EARTH, 6_678e3, 42_164e3).unwrap();
+
+        assert_close(transfer.departure_delta_v, 2_426., 1.);
+        assert_close(transfer.arrival_delta_v, 1_467., 1.);
+        assert_close(transfer.delta_v(), 3_893., 2.);
+        assert_close(transfer.transfer_time / 3_600., 5.275, 0.01);
+    }
+
+    #[test]
+    fn earth_to_mars() {
+        let transfer = Hohmann::between(MU_SUN, 1.496e11, 2.2794e11).unwrap();
+
+        assert_close(transfer.transfer_time / DAY, 259., 1.);
+        assert_close(transfer.phase_angle.to_degrees(), 44.3, 0.1);
+    }
+
+    #[test]
+    fn transfer_down_brakes() {
+        let up = Hohmann::between(MU_EARTH, 7e6, 9e6).unwrap();
+        let down = Hohmann::between(MU_EARTH, 9e6, 7e6).unwrap();
+
+        assert!(down.departure_delta_v < 0. && down.arrival_delta_v < 0.);
+        assert_close(down.delta_v(), up.delta_v(), 1e-9);
+        assert_close(down.transfer_time, up.transfer_time, 1e-9);
+        assert!(down.phase_angle < 0.);
+        assert!(Hohmann::between(MU_EARTH, 0., 7e6).is_none());
+    }
+
+    #[test]
+    fn spheres_of_influence() {
+        // The Earth around the Sun, and the Moon around the Earth.
+        assert_close(sphere_of_influence(1.496e11, 5.972e24, 1.989e30), 9.25e8, 0.01e8);
+        assert_close(sphere_of_influence(3.844e8, 7.342e22, 5.972e24), 6.62e7, 0.01e7);
+    }
+
+    #[test]
+    fn phase_angles() {
+        assert_close(phase_angle([1., 0.], [0., 2.]), PI / 2., 1e-12);
+        assert_close(phase_angle([1., 0.], [0., -2.]), -PI / 2., 1e-12);
+        assert_close(phase_angle([0., 3.], [-1., 0.]), PI / 2., 1e-12);
+    }
+
+    #[test]
+    fn time_to_earth_mars_window() {
+        let earth = (MU_SUN / 1.496e11f64.powi(3)).sqrt();
+        let mars = (MU_SUN / 2.2794e11f64.powi(3)).sqrt();
+        let synodic_period = 2. * PI / (earth - mars);
+        assert_close(synodic_period / DAY, 780., 1.);
+
+        let required = Hohmann::between(MU_SUN, 1.496e11, 2.2794e11).unwrap().phase_angle;
+
+        // Mars falls behind, so a window just missed comes back one synodic period later.
+        let just_missed = time_to_phase_angle(required - 1e-6, required, (earth, mars)).unwrap();
+        assert_close(just_missed / DAY, 780., 1.);
+
+        let quarter = time_to_phase_angle(required + PI / 2., required, (earth, mars)).unwrap();
+        assert_close(quarter, synodic_period / 4., 1.);
+    }
+
+    #[test]
+    fn time_to_phase_angle_without_relative_motion() {
+        assert_eq!(time_to_phase_angle(0., 1., (1e-3, 1e-3)), None);
+        assert_close(time_to_phase_angle(0., 1., (1e-3, 2e-3)).unwrap(), 1_000., 1e-9);
+    }
+}
@@ -1,39 +1,60 @@
+use std::{path::PathBuf, sync::OnceLock};
+
 use clap::Parser;
+use common::messages::{handshake::Role, transport::TransportKind};
 
-/// The server for Newton. It is responsible for running the simulation and sending the data to the clients.
+/// The client for Newton. It connects to a server, and displays the simulation.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Arguments {
-    /// The file to load the initial state from.
-    #[clap(short, long)]
-    pub system: String,
+    /// The host name or address of the server.
+    #[clap(short = 'H', long, default_value = "127.0.0.1")]
+    pub host: String,
 
-    /// The network interface to bind to.
-    #[clap(short, long, default_value = "0.0.0.0")]
-    pub interface: String,
-
-    /// The network port to bind to.
+    /// The port of the server.
     #[clap(short, long, default_value = "5000")]
     pub port: u16,
-    
-    /// The amount of softening applied to the simulation.
-    #[clap(long, default_value = "0.1")]
-    pub softening: f64,
-    
-    /// The number of updates per second to run the simulation at.
-    #[clap(short, long, default_value = "60")]
-    pub updates_per_second: u32,
-    
-    /// The target updates per second for the physics engine.
-    #[clap(short, long, default_value = "60")]
-    pub target_fps: u32,
-
-    /// The minimum steps per update for the physics engine.
-    #[clap(long, default_value = "10")]
-    pub min_spu: u32,
-
-    /// The maximum steps per update for the physics engine.
-    #[clap(long)]
-    pub max_spu: Option<u32>,
 
+    /// How snapshots are received: tcp, or udp. Should match the server's transport.
+    #[clap(long, default_value = "tcp")]
+    pub transport: TransportKind,
+
+    /// The name the server and other crew members know you by.
+    #[clap(short, long, default_value = "flight")]
+    pub callsign: String,
+
+    /// What to join as: pilot, observer or admin.
+    #[clap(short, long, default_value = "pilot")]
+    pub role: Role,
+
+    /// The id of the ship to take control of once connected.
+    #[clap(short, long)]
+    pub ship: Option<i32>,
+
+    /// The width of the window, in pixels.
+    #[clap(long, default_value = "1280")]
+    pub width: i32,
+
+    /// The height of the window, in pixels.
+    #[clap(long, default_value = "720")]
+    pub height: i32,
+
+    /// Starts in fullscreen.
+    #[clap(short, long)]
+    pub fullscreen: bool,
+
+    /// The directory to load assets from, instead of the one next to the executable.
+    #[clap(short, long)]
+    pub assets: Option<PathBuf>,
+
+    /// The file to read key and gamepad bindings from. The defaults are used if it doesn't exist.
+    #[clap(short, long, default_value = "bindings.ron")]
+    pub bindings: PathBuf,
+}
+
+/// The parsed command line.
+/// Parsed on first use, as the window configuration needs it before `main` runs.
+pub fn arguments() -> &'static Arguments {
+    static ARGUMENTS: OnceLock<Arguments> = OnceLock::new();
+    ARGUMENTS.get_or_init(Arguments::parse)
 }
@@ -0,0 +1,214 @@
+use std::{collections::HashMap, fs, path::Path, process};
+
+use common::data::inputs::NavMode;
+#[cfg(feature = "gamepad")]
+use gilrs::{Axis, Button};
+use macroquad::input::KeyCode;
+use ron::extensions::Extensions;
+use serde::Deserialize;
+
+/// Something the pilot can do with a key or a gamepad button.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub enum Action {
+    /// Raises the throttle for as long as it is held.
+    ThrottleUp,
+
+    /// Lowers the throttle for as long as it is held.
+    ThrottleDown,
+    CutThrottle,
+    FullThrottle,
+    NavMode(NavMode),
+
+    /// Makes the next planet, by id, the reference.
+    NextReference,
+    PreviousReference,
+
+    /// Goes back to the planet with the strongest pull as the reference.
+    AutoReference,
+
+    /// Makes the planet with this id the reference.
+    SetReference(i32),
+
+    /// Targets the next body, by id.
+    NextTarget,
+    PreviousTarget,
+    ClearTarget,
+
+    /// Targets the body with this id.
+    SetTarget(i32),
+    WarpUp,
+    WarpDown,
+
+    /// Shows or hides the velocity and acceleration arrows.
+    ToggleVectors,
+
+    /// Makes the camera follow the reference, then the ship, then nothing.
+    CycleCamera,
+    ZoomIn,
+    ZoomOut,
+}
+
+impl Action {
+    /// Whether the action repeats every frame while held, instead of once per press.
+    pub fn is_held(self) -> bool {
+        matches!(self, Action::ThrottleUp | Action::ThrottleDown)
+    }
+
+    /// Whether the action only changes what is displayed, and isn't sent to the server.
+    pub fn is_view(self) -> bool {
+        matches!(
+            self,
+            Action::ToggleVectors | Action::CycleCamera | Action::ZoomIn | Action::ZoomOut
+        )
+    }
+}
+
+/// Maps keys and gamepad buttons to actions.
+/// Read from the file given with `--bindings`. Every field is optional.
+/// Without the `gamepad` feature, the gamepad fields are ignored.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Bindings {
+    /// Keys by name, such as `"W"`, `"Key1"` or `"LeftShift"`.
+    pub keys: HashMap<String, Action>,
+    #[cfg(feature = "gamepad")]
+    pub buttons: HashMap<Button, Action>,
+
+    /// A gamepad axis that moves the throttle up or down, faster the further it is pushed.
+    #[cfg(feature = "gamepad")]
+    pub throttle_axis: Option<Axis>,
+
+    /// Deflections of `throttle_axis` smaller than this are ignored.
+    #[cfg(feature = "gamepad")]
+    pub dead_zone: f32,
+
+    /// How much of the full range the throttle moves per second, while held.
+    pub throttle_rate: f32,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let keys = [
+            ("W", Action::ThrottleUp),
+            ("S", Action::ThrottleDown),
+            ("X", Action::CutThrottle),
+            ("Z", Action::FullThrottle),
+            ("Key1", Action::NavMode(NavMode::MAN)),
+            ("Key2", Action::NavMode(NavMode::HOLD)),
+            ("Key3", Action::NavMode(NavMode::PROG)),
+            ("Key4", Action::NavMode(NavMode::RETRO)),
+            ("Key5", Action::NavMode(NavMode::RADOUT)),
+            ("Key6", Action::NavMode(NavMode::RADIN)),
+            ("Key7", Action::NavMode(NavMode::CCWPROG)),
+            ("Key8", Action::NavMode(NavMode::CWPROG)),
+            ("Key9", Action::NavMode(NavMode::TTARG)),
+            ("Key0", Action::NavMode(NavMode::ATARG)),
+            ("E", Action::NextReference),
+            ("Q", Action::PreviousReference),
+            ("C", Action::AutoReference),
+            ("T", Action::NextTarget),
+            ("R", Action::PreviousTarget),
+            ("Backspace", Action::ClearTarget),
+            ("Period", Action::WarpUp),
+            ("Comma", Action::WarpDown),
+            ("V", Action::ToggleVectors),
+            ("F", Action::CycleCamera),
+            ("Equal", Action::ZoomIn),
+            ("Minus", Action::ZoomOut),
+        ];
+
+        #[cfg(feature = "gamepad")]
+        let buttons = [
+            (Button::RightTrigger2, Action::ThrottleUp),
+            (Button::LeftTrigger2, Action::ThrottleDown),
+            (Button::East, Action::CutThrottle),
+            (Button::North, Action::FullThrottle),
+            (Button::South, Action::NavMode(NavMode::HOLD)),
+            (Button::West, Action::NavMode(NavMode::MAN)),
+            (Button::DPadUp, Action::NavMode(NavMode::PROG)),
+            (Button::DPadDown, Action::NavMode(NavMode::RETRO)),
+            (Button::DPadRight, Action::NavMode(NavMode::RADOUT)),
+            (Button::DPadLeft, Action::NavMode(NavMode::RADIN)),
+            (Button::RightTrigger, Action::NextTarget),
+            (Button::LeftTrigger, Action::PreviousTarget),
+            (Button::RightThumb, Action::NextReference),
+            (Button::LeftThumb, Action::PreviousReference),
+            (Button::Select, Action::ToggleVectors),
+            (Button::Start, Action::CycleCamera),
+        ];
+
+        Bindings {
+            keys: keys
+                .into_iter()
+                .map(|(key, action)| (key.to_owned(), action))
+                .collect(),
+            #[cfg(feature = "gamepad")]
+            buttons: buttons.into_iter().collect(),
+            #[cfg(feature = "gamepad")]
+            throttle_axis: Some(Axis::LeftStickY),
+            #[cfg(feature = "gamepad")]
+            dead_zone: 0.15,
+            throttle_rate: 0.5,
+        }
+    }
+}
+
+impl Bindings {
+    /// The bound keys, with the names resolved to key codes.
+    /// Unknown names are reported and left out.
+    pub fn key_codes(&self) -> Vec<(KeyCode, Action)> {
+        self.keys
+            .iter()
+            .filter_map(|(name, action)| match key_code(name) {
+                Some(key) => Some((key, *action)),
+                None => {
+                    log::warn!("Unknown key '{}' in bindings", name);
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// Loads the bindings from `path`, or uses the default ones if there is no such file.
+pub fn load_bindings(path: &Path) -> Bindings {
+    let bindings_file = match fs::read_to_string(path) {
+        Ok(val) => val,
+        Err(_) => {
+            log::info!("No bindings at {}, using the default ones", path.display());
+            return Bindings::default();
+        }
+    };
+
+    let options = ron::Options::default().with_default_extension(Extensions::IMPLICIT_SOME);
+
+    match options.from_str(bindings_file.as_str()) {
+        Ok(val) => val,
+        Err(e) => {
+            log::error!("Failed to parse bindings file: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Finds a key by the name of its `KeyCode` variant.
+fn key_code(name: &str) -> Option<KeyCode> {
+    macro_rules! key_codes {
+        ($($key:ident),* $(,)?) => {
+            match name {
+                $(stringify!($key) => Some(KeyCode::$key),)*
+                _ => None,
+            }
+        };
+    }
+
+    key_codes!(
+        Space, Apostrophe, Comma, Minus, Period, Slash, Key0, Key1, Key2, Key3, Key4, Key5, Key6,
+        Key7, Key8, Key9, Semicolon, Equal, A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R,
+        S, T, U, V, W, X, Y, Z, LeftBracket, Backslash, RightBracket, GraveAccent, Escape, Enter,
+        Tab, Backspace, Insert, Delete, Right, Left, Down, Up, PageUp, PageDown, Home, End, F1,
+        F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12, Kp0, Kp1, Kp2, Kp3, Kp4, Kp5, Kp6, Kp7,
+        Kp8, Kp9, KpDecimal, KpDivide, KpMultiply, KpSubtract, KpAdd, KpEnter, KpEqual,
+        LeftShift, LeftControl, LeftAlt, RightShift, RightControl, RightAlt,
+    )
+}
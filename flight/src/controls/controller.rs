@@ -0,0 +1,329 @@
+use std::time::{Duration, Instant};
+
+use common::{
+    data::{
+        clock::WARP_LEVELS,
+        inputs::{inputs_for, Inputs},
+        state::State,
+    },
+    messages::from_client::FromClientMessage,
+};
+#[cfg(feature = "gamepad")]
+use gilrs::{EventType, Gilrs};
+use macroquad::input::{is_key_down, is_key_pressed, KeyCode};
+
+use crate::{data::client_state::ClientState, networking::client::Commands};
+
+use super::bindings::{Action, Bindings};
+
+/// The shortest time between two throttle commands.
+/// Changes in between are merged, so holding a key doesn't flood the server.
+const THROTTLE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long after the pilot last changed an input the server's value is trusted again.
+/// Until then, snapshots may still predate the change.
+const SYNC_DELAY: Duration = Duration::from_secs(1);
+
+/// Turns keyboard and gamepad input into commands for the controlled ship,
+/// which the network thread sends to the server.
+///
+/// Held inputs, such as the throttle, are rate-limited.
+/// Everything else is sent once per press.
+pub struct Controller {
+    keys: Vec<(KeyCode, Action)>,
+    bindings: Bindings,
+
+    /// `None` when gamepads aren't supported on this system.
+    #[cfg(feature = "gamepad")]
+    gilrs: Option<Gilrs>,
+    commands: Commands,
+
+    /// The ship commands are sent for, as of the last update.
+    ship: Option<i32>,
+
+    /// Actions pushed since the last update.
+    queued: Vec<Action>,
+
+    /// The inputs of the ship, including changes the server may not have applied yet.
+    inputs: Option<Inputs>,
+
+    /// When the pilot last changed `inputs`.
+    touched: Option<Instant>,
+
+    /// Whether the throttle changed since it was last sent.
+    throttle_changed: bool,
+
+    /// When the throttle was last sent.
+    throttle_sent: Option<Instant>,
+}
+
+impl Controller {
+    pub fn new(bindings: Bindings, commands: Commands) -> Controller {
+        #[cfg(feature = "gamepad")]
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(e) => {
+                log::warn!("Gamepads are unavailable: {}", e);
+                None
+            }
+        };
+
+        Controller {
+            keys: bindings.key_codes(),
+            bindings,
+            #[cfg(feature = "gamepad")]
+            gilrs,
+            commands,
+            ship: None,
+            queued: Vec::new(),
+            inputs: None,
+            touched: None,
+            throttle_changed: false,
+            throttle_sent: None,
+        }
+    }
+
+    /// Queues an action that doesn't come from a binding, such as a click on a body.
+    /// It is handled on the next update.
+    pub fn push(&mut self, action: Action) {
+        self.queued.push(action);
+    }
+
+    /// Reads this frame's input, and sends the commands it calls for.
+    /// `state` is the one being displayed, and `dt` the duration of the last frame, in seconds.
+    ///
+    /// The reference and target in `client_state` are updated to match the ship's inputs.
+    ///
+    /// Returns the actions pressed this frame that only affect the view, for the caller to apply.
+    pub fn update(
+        &mut self,
+        state: &State,
+        client_state: &mut ClientState,
+        now: Instant,
+        dt: f32,
+    ) -> Vec<Action> {
+        let mut pressed = std::mem::take(&mut self.queued);
+        let mut throttle_input = 0.;
+
+        for (key, action) in &self.keys {
+            if action.is_held() {
+                if is_key_down(*key) {
+                    throttle_input += held_direction(*action);
+                }
+            } else if is_key_pressed(*key) {
+                pressed.push(*action);
+            }
+        }
+
+        #[cfg(feature = "gamepad")]
+        {
+            throttle_input += self.read_gamepads(&mut pressed);
+        }
+
+        let view = pressed
+            .iter()
+            .copied()
+            .filter(|action| action.is_view())
+            .collect();
+
+        // Time warp is shared by everyone, so it doesn't need a ship.
+        for action in &pressed {
+            let warp = state.clock.requested_warp;
+            match action {
+                Action::WarpUp if warp + 1 < WARP_LEVELS.len() => {
+                    self.commands.send(FromClientMessage::Warp(warp + 1));
+                }
+                Action::WarpDown if warp > 0 => {
+                    self.commands.send(FromClientMessage::Warp(warp - 1));
+                }
+                _ => {}
+            }
+        }
+
+        if client_state.ship != self.ship {
+            self.ship = client_state.ship;
+            self.inputs = None;
+            self.touched = None;
+            self.throttle_changed = false;
+        }
+
+        let Some(ship) = self.ship else {
+            // Without a ship, the reference and target are only used for display.
+            let followed = client_state.followed_ship(state).map(|s| s.object.id);
+            for action in pressed {
+                let current = client_state.reference_planet(state).map(|p| p.object.id);
+                if let Some(reference) = reference_after(state, current, action) {
+                    client_state.reference = reference;
+                }
+                if let Some(target) = target_after(state, followed, client_state.target, action) {
+                    client_state.target = target;
+                }
+            }
+            return view;
+        };
+
+        let synced = self
+            .touched
+            .is_none_or(|touched| now.duration_since(touched) > SYNC_DELAY);
+        let mut inputs = match self.inputs {
+            Some(inputs) if !synced => inputs,
+            _ => inputs_for(&state.inputs, ship),
+        };
+        let before = inputs;
+
+        let throttle_input = throttle_input.clamp(-1., 1.);
+        inputs.throttle += throttle_input * self.bindings.throttle_rate * dt;
+
+        for action in pressed {
+            match action {
+                Action::CutThrottle => inputs.throttle = 0.,
+                Action::FullThrottle => inputs.throttle = 1.,
+                Action::NavMode(navmode) => {
+                    inputs.navmode = navmode;
+                    self.commands
+                        .send(FromClientMessage::NavMode { ship, navmode });
+                }
+                _ => {
+                    let current = inputs
+                        .reference
+                        .or_else(|| client_state.reference_planet(state).map(|p| p.object.id));
+                    if let Some(reference) = reference_after(state, current, action) {
+                        inputs.reference = reference;
+                        self.commands
+                            .send(FromClientMessage::Reference { ship, reference });
+                    }
+
+                    if let Some(target) = target_after(state, Some(ship), inputs.target, action) {
+                        inputs.target = target;
+                        self.commands.send(FromClientMessage::Target { ship, target });
+                    }
+                }
+            }
+        }
+        inputs.throttle = inputs.throttle.clamp(0., 1.);
+
+        if inputs != before {
+            self.touched = Some(now);
+        }
+        if inputs.throttle != before.throttle {
+            self.throttle_changed = true;
+        }
+        self.inputs = Some(inputs);
+        client_state.reference = inputs.reference;
+        client_state.target = inputs.target;
+
+        let due = self
+            .throttle_sent
+            .is_none_or(|sent| now.duration_since(sent) >= THROTTLE_INTERVAL);
+        if self.throttle_changed && due {
+            self.commands.send(FromClientMessage::Throttle {
+                ship,
+                throttle: inputs.throttle,
+            });
+            self.throttle_changed = false;
+            self.throttle_sent = Some(now);
+        }
+
+        view
+    }
+
+    /// Pushes the actions of the gamepad buttons pressed since the last update to `pressed`.
+    /// Returns how the held buttons and the throttle axis move the throttle.
+    #[cfg(feature = "gamepad")]
+    fn read_gamepads(&mut self, pressed: &mut Vec<Action>) -> f32 {
+        let mut throttle_input = 0.;
+        let Some(gilrs) = &mut self.gilrs else {
+            return throttle_input;
+        };
+
+        while let Some(event) = gilrs.next_event() {
+            if let EventType::ButtonPressed(button, _) = event.event {
+                match self.bindings.buttons.get(&button) {
+                    Some(action) if !action.is_held() => pressed.push(*action),
+                    _ => {}
+                }
+            }
+        }
+
+        for (_, gamepad) in gilrs.gamepads() {
+            for (button, action) in &self.bindings.buttons {
+                if action.is_held() && gamepad.is_pressed(*button) {
+                    throttle_input += held_direction(*action);
+                }
+            }
+
+            if let Some(axis) = self.bindings.throttle_axis {
+                let value = gamepad.value(axis);
+                if value.abs() > self.bindings.dead_zone {
+                    throttle_input += value;
+                }
+            }
+        }
+
+        throttle_input
+    }
+}
+
+/// Which way a held action moves the throttle.
+fn held_direction(action: Action) -> f32 {
+    match action {
+        Action::ThrottleUp => 1.,
+        Action::ThrottleDown => -1.,
+        _ => 0.,
+    }
+}
+
+/// The next id after `current` in `ids`, or the previous one, wrapping around.
+/// Starts from either end when there is no current id.
+fn cycle(mut ids: Vec<i32>, current: Option<i32>, forward: bool) -> Option<i32> {
+    ids.sort_unstable();
+
+    match (current, forward) {
+        (Some(current), true) => ids.iter().find(|id| **id > current).or(ids.first()).copied(),
+        (Some(current), false) => ids.iter().rev().find(|id| **id < current).or(ids.last()).copied(),
+        (None, true) => ids.first().copied(),
+        (None, false) => ids.last().copied(),
+    }
+}
+
+/// The reference after `action`, or `None` if it isn't about the reference.
+/// `Some(None)` stands for the planet with the strongest pull.
+/// Only planets can be references.
+fn reference_after(state: &State, current: Option<i32>, action: Action) -> Option<Option<i32>> {
+    let planets = || state.planets.iter().map(|p| p.object.id).collect();
+
+    match action {
+        Action::NextReference => Some(cycle(planets(), current, true)),
+        Action::PreviousReference => Some(cycle(planets(), current, false)),
+        Action::AutoReference => Some(None),
+        Action::SetReference(id) if state.planets.iter().any(|p| p.object.id == id) => {
+            Some(Some(id))
+        }
+        _ => None,
+    }
+}
+
+/// The target after `action`, or `None` if it isn't about the target.
+/// Any body but `ship` can be targeted.
+fn target_after(
+    state: &State,
+    ship: Option<i32>,
+    current: Option<i32>,
+    action: Action,
+) -> Option<Option<i32>> {
+    let bodies = || {
+        state
+            .objects()
+            .map(|o| o.id)
+            .filter(|id| Some(*id) != ship)
+            .collect()
+    };
+
+    match action {
+        Action::NextTarget => Some(cycle(bodies(), current, true)),
+        Action::PreviousTarget => Some(cycle(bodies(), current, false)),
+        Action::ClearTarget => Some(None),
+        Action::SetTarget(id) if Some(id) != ship => Some(Some(id)),
+        _ => None,
+    }
+}
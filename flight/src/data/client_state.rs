@@ -1,3 +1,51 @@
+use common::{
+    data::{
+        object::{Object, Planet, Ship},
+        state::State,
+    },
+    orbit::{gravity::dominant_planet, position_of},
+};
+
+/// What the pilot flies and looks at, shared by the controls and the renderer.
+///
+/// While we control a ship, the reference and target follow that ship's inputs on the server.
+/// Otherwise they are only used for display.
+#[derive(Debug, Default)]
 pub struct ClientState {
-    pub center: i32,
-}
\ No newline at end of file
+    /// The ship we control.
+    pub ship: Option<i32>,
+
+    /// The planet motion is shown relative to, which the camera follows by default.
+    /// When unset, the planet pulling the followed ship hardest is used.
+    pub reference: Option<i32>,
+
+    /// The body shown relative to the followed ship, for rendezvous.
+    pub target: Option<i32>,
+}
+
+impl ClientState {
+    /// The ship whose data is shown: the one we control, or the first one when we control none.
+    pub fn followed_ship<'s>(&self, state: &'s State) -> Option<&'s Ship> {
+        match self.ship {
+            Some(id) => state.ships.iter().find(|s| s.object.id == id),
+            None => state.ships.first(),
+        }
+    }
+
+    /// The planet motion is shown relative to.
+    pub fn reference_planet<'s>(&self, state: &'s State) -> Option<&'s Planet> {
+        if let Some(id) = self.reference {
+            return state.planets.iter().find(|p| p.object.id == id);
+        }
+
+        match self.followed_ship(state) {
+            Some(ship) => dominant_planet(position_of(&ship.object), &state.planets),
+            None => state.planets.first(),
+        }
+    }
+
+    pub fn target_object<'s>(&self, state: &'s State) -> Option<&'s Object> {
+        let target = self.target?;
+        state.objects().find(|o| o.id == target)
+    }
+}
@@ -1 +1,3 @@
-pub mod client_state;
\ No newline at end of file
+pub mod client_state;
+pub mod snapshots;
+pub mod trajectory;
\ No newline at end of file
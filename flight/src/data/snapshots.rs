@@ -0,0 +1,194 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use common::data::object::Object;
+use common::data::state::State;
+use common::orbit::wrap_angle;
+
+/// How many received snapshots are kept for interpolation.
+const CAPACITY: usize = 32;
+
+/// How far behind the latest snapshot the displayed state is, in update intervals.
+/// Leaves room for one late or lost update before falling back to extrapolation.
+const INTERPOLATION_DELAY: f64 = 2.0;
+
+/// The longest the displayed state is extrapolated past the latest snapshot, in wall seconds.
+const MAX_EXTRAPOLATION: f64 = 0.5;
+
+/// When the playback clock drifts further than this from where it should be,
+/// in wall seconds, it jumps instead of catching up smoothly.
+const MAX_DRIFT: f64 = 0.5;
+
+/// Fraction of the playback clock's drift corrected each frame.
+const DRIFT_CORRECTION: f64 = 0.05;
+
+/// Smoothing factor of the measured interval between updates.
+const INTERVAL_SMOOTHING: f64 = 0.1;
+
+struct Snapshot {
+    received: Instant,
+    state: State,
+}
+
+/// Buffers the states received from the server, and gives the graphics thread a state
+/// for any frame, interpolated between snapshots or extrapolated past the latest one.
+///
+/// Snapshots are placed on a timeline by their simulation time, so time warp is handled
+/// the same way as real time.
+pub struct SnapshotBuffer {
+    snapshots: VecDeque<Snapshot>,
+
+    /// Average wall time between two received snapshots, in seconds.
+    interval: Option<f64>,
+
+    /// The simulation time last displayed, and when.
+    playback: Option<(Instant, f64)>,
+}
+
+impl Default for SnapshotBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SnapshotBuffer {
+    pub fn new() -> SnapshotBuffer {
+        SnapshotBuffer {
+            snapshots: VecDeque::with_capacity(CAPACITY),
+            interval: None,
+            playback: None,
+        }
+    }
+
+    /// Forgets every snapshot, for instance after losing the connection.
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.interval = None;
+        self.playback = None;
+    }
+
+    pub fn push(&mut self, state: State, received: Instant) {
+        if let Some(latest) = self.snapshots.back() {
+            if state.clock.time < latest.state.clock.time {
+                return;
+            }
+
+            let gap = received.duration_since(latest.received).as_secs_f64();
+            self.interval = Some(match self.interval {
+                Some(interval) => interval + (gap - interval) * INTERVAL_SMOOTHING,
+                None => gap,
+            });
+        }
+
+        if self.snapshots.len() == CAPACITY {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(Snapshot { received, state });
+    }
+
+    /// The state to display at `now`.
+    pub fn sample(&mut self, now: Instant) -> Option<State> {
+        let latest = self.snapshots.back()?;
+        // An unknown warp is played back in real time, rather than not at all.
+        let warp = latest.state.clock.warp_factor().unwrap_or(1.);
+        let delay = INTERPOLATION_DELAY * self.interval.unwrap_or(0.);
+
+        let since_latest = now.duration_since(latest.received).as_secs_f64();
+        let target = latest.state.clock.time + (since_latest - delay) * warp;
+
+        // Advance the playback clock at the warp rate, nudging it towards the target
+        // rather than following it, so jitter in arrival times doesn't show.
+        let time = match self.playback {
+            Some((at, time)) => {
+                let predicted = time + now.duration_since(at).as_secs_f64() * warp;
+                let drift = target - predicted;
+                if drift.abs() > MAX_DRIFT * warp {
+                    target
+                } else {
+                    predicted + drift * DRIFT_CORRECTION
+                }
+            }
+            None => target,
+        };
+        self.playback = Some((now, time));
+
+        let after = self.snapshots.iter().position(|s| s.state.clock.time >= time);
+
+        match after {
+            Some(0) => Some(self.snapshots[0].state.clone()),
+            Some(i) => {
+                let a = &self.snapshots[i - 1].state;
+                let b = &self.snapshots[i].state;
+                Some(interpolate(a, b, time))
+            }
+            None => {
+                let dt = (time - latest.state.clock.time).min(MAX_EXTRAPOLATION * warp);
+                Some(extrapolate(&latest.state, dt))
+            }
+        }
+    }
+}
+
+/// The state between `a` and `b` at simulation time `time`.
+/// Positions follow a cubic Hermite curve through both snapshots' velocities,
+/// which keeps orbits round even with few updates per revolution.
+fn interpolate(a: &State, b: &State, time: f64) -> State {
+    let dt = b.clock.time - a.clock.time;
+    if dt <= 0. {
+        return b.clone();
+    }
+
+    let s = ((time - a.clock.time) / dt).clamp(0., 1.);
+    let h00 = 2. * s.powi(3) - 3. * s.powi(2) + 1.;
+    let h10 = s.powi(3) - 2. * s.powi(2) + s;
+    let h01 = -2. * s.powi(3) + 3. * s.powi(2);
+    let h11 = s.powi(3) - s.powi(2);
+
+    let blend = |from: &Object, to: &mut Object| {
+        to.position = &from.position * h00
+            + &from.velocity * (h10 * dt)
+            + &to.position * h01
+            + &to.velocity * (h11 * dt);
+        to.velocity = &from.velocity + (&to.velocity - &from.velocity) * s;
+        to.acceleration = &from.acceleration + (&to.acceleration - &from.acceleration) * s;
+        to.heading = from.heading + wrap_angle(to.heading - from.heading) * s;
+        to.spin = from.spin + (to.spin - from.spin) * s;
+    };
+
+    let mut state = b.clone();
+    for ship in state.ships.iter_mut() {
+        if let Some(from) = a.ships.iter().find(|s| s.object.id == ship.object.id) {
+            blend(&from.object, &mut ship.object);
+        }
+    }
+    for planet in state.planets.iter_mut() {
+        if let Some(from) = a.planets.iter().find(|p| p.object.id == planet.object.id) {
+            blend(&from.object, &mut planet.object);
+        }
+    }
+    state.clock.time = time;
+
+    state
+}
+
+/// Dead-reckons `state` forward by `dt` simulated seconds, from velocities and accelerations.
+fn extrapolate(state: &State, dt: f64) -> State {
+    let advance = |object: &mut Object| {
+        object.position = &object.position
+            + &object.velocity * dt
+            + &object.acceleration * (0.5 * dt * dt);
+        object.velocity = &object.velocity + &object.acceleration * dt;
+        object.heading += object.spin * dt;
+    };
+
+    let mut state = state.clone();
+    for ship in state.ships.iter_mut() {
+        advance(&mut ship.object);
+    }
+    for planet in state.planets.iter_mut() {
+        advance(&mut planet.object);
+    }
+    state.clock.time += dt;
+
+    state
+}
@@ -0,0 +1,223 @@
+use std::f64::consts::PI;
+
+use common::{
+    data::{
+        inputs::{inputs_for, NavMode},
+        object::{Planet, Ship, ShipType},
+        state::State,
+    },
+    orbit::{
+        add,
+        elements::Elements,
+        gravity::{acceleration, gravitational_parameter},
+        norm, position_of, relative::Relative, scale, sub, Vector,
+    },
+};
+
+/// Number of points a predicted trajectory is drawn with.
+const POINTS: usize = 256;
+
+/// Open orbits are drawn until they are this many times further away than the ship is now.
+const OPEN_ORBIT_EXTENT: f64 = 10.;
+
+/// Fraction of the current orbital period covered while thrusting.
+/// Beyond that, the prediction drifts too far from what the pilot will actually do.
+const THRUST_HORIZON: f64 = 0.25;
+
+/// How far ahead a thrusting ship is predicted when its orbit has no period,
+/// such as an open or radial one, in seconds.
+const OPEN_THRUST_HORIZON: f64 = 600.;
+
+/// Where a ship is headed, relative to a reference body.
+pub struct Trajectory {
+    /// The id of the body the trajectory is relative to.
+    pub reference: i32,
+
+    /// Points of the path ahead, relative to the reference, starting at the ship.
+    pub points: Vec<Vector>,
+
+    /// The next periapsis on the path, relative to the reference.
+    pub periapsis: Option<Vector>,
+
+    /// The next apoapsis on the path, relative to the reference.
+    pub apoapsis: Option<Vector>,
+
+    /// Whether the path ends on the reference's surface.
+    pub impact: bool,
+}
+
+/// Predicts the trajectory of `ship` relative to `reference`.
+///
+/// A coasting ship follows a conic. A thrusting one is propagated numerically for a
+/// short while, through the gravity of every planet, assuming the throttle stays where it is.
+/// Returns `None` for landed ships, and for coasting ships on a radial path, which has no conic.
+pub fn predict(state: &State, ship: &Ship, reference: &Planet) -> Option<Trajectory> {
+    if ship.landed.is_some() {
+        return None;
+    }
+
+    let relative = Relative::between(&ship.object, &reference.object);
+    if relative.distance() <= reference.radius {
+        return None;
+    }
+
+    let elements = relative.elements(gravitational_parameter(reference.object.mass));
+
+    let throttle = inputs_for(&state.inputs, ship.object.id).throttle;
+    if throttle > 0. && ship.fuel_mass() > 0. {
+        Some(propagate(state, ship, reference, elements.as_ref(), relative))
+    } else {
+        Some(conic(reference, &elements?, relative.distance()))
+    }
+}
+
+/// Samples the conic ahead of the ship, up to the surface if it gets there.
+fn conic(reference: &Planet, elements: &Elements, radius: f64) -> Trajectory {
+    let start = elements.true_anomaly;
+
+    let mut end = if elements.is_bound() {
+        start + 2. * PI
+    } else {
+        // The true anomaly at which the ship is far enough to stop drawing.
+        let far = radius.max(elements.periapsis()) * OPEN_ORBIT_EXTENT;
+        let cos = ((elements.semi_latus_rectum() / far - 1.) / elements.eccentricity).clamp(-1., 1.);
+        cos.acos().min(elements.max_true_anomaly()).max(start)
+    };
+
+    // Where the path crosses the surface on the way down, if it does.
+    let mut impact = false;
+    if elements.periapsis() < reference.radius && elements.eccentricity > 0. {
+        let cos = (elements.semi_latus_rectum() / reference.radius - 1.) / elements.eccentricity;
+        let surface = -cos.clamp(-1., 1.).acos();
+        let until_surface = next_occurrence(start, surface);
+        if until_surface <= end {
+            end = until_surface;
+            impact = true;
+        }
+    }
+
+    let points = (0..=POINTS)
+        .map(|i| elements.position_at(start + (end - start) * i as f64 / POINTS as f64))
+        .collect();
+
+    let apsis = |anomaly: f64| {
+        (next_occurrence(start, anomaly) <= end).then(|| elements.position_at(anomaly))
+    };
+
+    Trajectory {
+        reference: reference.object.id,
+        points,
+        periapsis: apsis(0.),
+        apoapsis: if elements.is_bound() { apsis(PI) } else { None },
+        impact,
+    }
+}
+
+/// Integrates the path of a thrusting ship with RK4, in the frame of the reference.
+/// Other planets are assumed to keep their place relative to the reference,
+/// which holds over the short span predicted.
+/// `elements` are those of the current orbit, if it has any, and set how far ahead to go.
+fn propagate(
+    state: &State,
+    ship: &Ship,
+    reference: &Planet,
+    elements: Option<&Elements>,
+    relative: Relative,
+) -> Trajectory {
+    let ShipType::HAB(data) = &ship.ship_type;
+    let inputs = inputs_for(&state.inputs, ship.object.id);
+    let origin = position_of(&reference.object);
+
+    let horizon = elements
+        .and_then(Elements::period)
+        .map_or(OPEN_THRUST_HORIZON, |period| period * THRUST_HORIZON);
+    let dt = horizon / POINTS as f64;
+
+    // The reference feels the other planets too, and the frame moves with it.
+    let frame_acceleration = acceleration(origin, &state.planets);
+    let heading = ship.object.heading;
+
+    let mut fuel = data.fuel_mass;
+    let derivative = |position: Vector, velocity: Vector, fuel: f64| -> (Vector, Vector) {
+        let gravity = sub(acceleration(add(origin, position), &state.planets), frame_acceleration);
+        if fuel <= 0. {
+            return (velocity, gravity);
+        }
+
+        let direction = thrust_direction(inputs.navmode, heading, position, velocity);
+        let thrust = data.thrust as f64 * inputs.throttle as f64 / (data.dry_mass + fuel);
+        (velocity, add(gravity, scale(direction, thrust)))
+    };
+
+    let mut points = Vec::with_capacity(POINTS + 1);
+    let mut radii = Vec::with_capacity(POINTS + 1);
+    let (mut r, mut v) = (relative.position, relative.velocity);
+    let mut impact = false;
+    points.push(r);
+    radii.push(norm(r));
+
+    for _ in 0..POINTS {
+        let (k1r, k1v) = derivative(r, v, fuel);
+        let (k2r, k2v) = derivative(add(r, scale(k1r, dt / 2.)), add(v, scale(k1v, dt / 2.)), fuel);
+        let (k3r, k3v) = derivative(add(r, scale(k2r, dt / 2.)), add(v, scale(k2v, dt / 2.)), fuel);
+        let (k4r, k4v) = derivative(add(r, scale(k3r, dt)), add(v, scale(k3v, dt)), fuel);
+
+        let weighted = |a: Vector, b: Vector, c: Vector, d: Vector| {
+            scale(add(add(a, scale(add(b, c), 2.)), d), dt / 6.)
+        };
+        r = add(r, weighted(k1r, k2r, k3r, k4r));
+        v = add(v, weighted(k1v, k2v, k3v, k4v));
+        fuel -= data.mass_flow() * inputs.throttle as f64 * dt;
+
+        points.push(r);
+        radii.push(norm(r));
+
+        if norm(r) <= reference.radius {
+            impact = true;
+            break;
+        }
+    }
+
+    // The first turning points of the distance to the reference.
+    let turning = |closer: fn(f64, f64) -> bool| {
+        (1..radii.len().saturating_sub(1))
+            .find(|&i| closer(radii[i], radii[i - 1]) && closer(radii[i], radii[i + 1]))
+            .map(|i| points[i])
+    };
+
+    Trajectory {
+        reference: reference.object.id,
+        periapsis: turning(|a, b| a < b),
+        apoapsis: turning(|a, b| a > b),
+        points,
+        impact,
+    }
+}
+
+/// The direction the engine pushes in.
+/// Modes relative to the reference are followed along the path, others keep the current heading.
+fn thrust_direction(navmode: NavMode, heading: f64, position: Vector, velocity: Vector) -> Vector {
+    let unit = |v: Vector| {
+        let length = norm(v);
+        if length > 0. {
+            scale(v, 1. / length)
+        } else {
+            [heading.cos(), heading.sin()]
+        }
+    };
+
+    match navmode {
+        NavMode::PROG => unit(velocity),
+        NavMode::RETRO => scale(unit(velocity), -1.),
+        NavMode::RADOUT => unit(position),
+        NavMode::RADIN => scale(unit(position), -1.),
+        NavMode::CCWPROG => unit([-position[1], position[0]]),
+        NavMode::CWPROG => unit([position[1], -position[0]]),
+        _ => [heading.cos(), heading.sin()],
+    }
+}
+
+/// The first angle equal to `angle` modulo a full turn, at or after `start`.
+fn next_occurrence(start: f64, angle: f64) -> f64 {
+    start + (angle - start).rem_euclid(2. * PI)
+}
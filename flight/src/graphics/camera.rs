@@ -0,0 +1,256 @@
+use std::fmt;
+
+use common::orbit::Vector;
+use macroquad::prelude::*;
+
+/// How far the mouse can move between press and release for a click, in pixels.
+/// Further than that, it is a drag.
+const CLICK_DISTANCE: f32 = 5.;
+
+/// The closest zoom, as the base 10 logarithm of pixels per meter.
+const MAX_ZOOM: f64 = 2.;
+
+/// The furthest zoom, as the base 10 logarithm of pixels per meter.
+/// A pixel then spans about seven astronomical units.
+const MIN_ZOOM: f64 = -12.;
+
+/// How much one notch of the mouse wheel zooms, in decades.
+const WHEEL_ZOOM: f64 = 0.1;
+
+/// How much one press of a zoom key zooms, in decades.
+const KEY_ZOOM: f64 = 0.5;
+
+/// How quickly the zoom catches up with the one asked for, per second.
+/// The larger, the snappier.
+const ZOOM_SPEED: f64 = 12.;
+
+/// The share of the screen height the reference fills when the camera first sees it.
+const INITIAL_FILL: f64 = 0.3;
+
+/// What the camera stays centered on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Follow {
+    /// The reference planet.
+    Reference,
+
+    /// The ship whose data is shown.
+    Ship,
+
+    /// Nothing: the camera stays where it was panned to.
+    Free,
+}
+
+impl fmt::Display for Follow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Follow::Reference => write!(f, "reference"),
+            Follow::Ship => write!(f, "ship"),
+            Follow::Free => write!(f, "free"),
+        }
+    }
+}
+
+/// A view of the world that zooms over many orders of magnitude.
+///
+/// The center is kept in world coordinates, as `f64`.
+/// Positions are made relative to it before being cast to `f32` for drawing,
+/// so bodies far from the origin are drawn as precisely as those near it.
+pub struct Camera {
+    /// The world position at the center of the screen.
+    center: Vector,
+
+    /// The zoom, as the base 10 logarithm of pixels per meter.
+    zoom: f64,
+
+    /// The zoom being eased towards.
+    target_zoom: f64,
+
+    /// The screen position that stays put while zooming, or `None` for the center.
+    anchor: Option<Vec2>,
+    follow: Follow,
+
+    /// Whether the zoom was fitted to a reference yet.
+    fitted: bool,
+
+    /// Where the left mouse button went down, and whether it has been dragged since.
+    pressed: Option<(Vec2, bool)>,
+
+    /// The mouse position of the last frame, in pixels.
+    last_mouse: Vec2,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            center: [0., 0.],
+            zoom: 0.,
+            target_zoom: 0.,
+            anchor: None,
+            follow: Follow::Reference,
+            fitted: false,
+            pressed: None,
+            last_mouse: mouse_position().into(),
+        }
+    }
+}
+
+impl Camera {
+    pub fn follow(&self) -> Follow {
+        self.follow
+    }
+
+    /// Follows the reference, then the ship, then nothing.
+    pub fn cycle_follow(&mut self) {
+        self.follow = match self.follow {
+            Follow::Reference => Follow::Ship,
+            Follow::Ship => Follow::Free,
+            Follow::Free => Follow::Reference,
+        };
+    }
+
+    /// Zooms in by `decades`, or out when negative, around the center of the screen.
+    pub fn zoom_by(&mut self, decades: f64) {
+        self.target_zoom = (self.target_zoom + decades).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.anchor = None;
+    }
+
+    /// Zooms in by one key press.
+    pub fn zoom_in(&mut self) {
+        self.zoom_by(KEY_ZOOM);
+    }
+
+    /// Zooms out by one key press.
+    pub fn zoom_out(&mut self) {
+        self.zoom_by(-KEY_ZOOM);
+    }
+
+    /// Zooms with the wheel, and pans by dragging with the left button, which frees the camera.
+    /// Returns where the left button was clicked, if it was released this frame without dragging.
+    pub fn handle_mouse(&mut self) -> Option<Vec2> {
+        let mouse: Vec2 = mouse_position().into();
+        let delta = mouse - self.last_mouse;
+        self.last_mouse = mouse;
+
+        let wheel = mouse_wheel().1;
+        if wheel != 0. {
+            // Wheels report anything from one to a hundred per notch, depending on the platform.
+            self.zoom_by(WHEEL_ZOOM * wheel.signum() as f64);
+
+            // A free camera zooms towards the mouse, a following one keeps its body centered.
+            if self.follow == Follow::Free {
+                self.anchor = Some(mouse);
+            }
+        }
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            self.pressed = Some((mouse, false));
+        }
+
+        if let Some((pressed_at, dragging)) = self.pressed {
+            if dragging {
+                self.pan(delta);
+            } else if pressed_at.distance(mouse) >= CLICK_DISTANCE {
+                self.pressed = Some((pressed_at, true));
+                self.follow = Follow::Free;
+                self.pan(mouse - pressed_at);
+            }
+        }
+
+        if is_mouse_button_released(MouseButton::Left) {
+            return match self.pressed.take() {
+                Some((_, false)) => Some(mouse),
+                _ => None,
+            };
+        }
+
+        None
+    }
+
+    /// Moves the view along with a drag of `delta` pixels.
+    fn pan(&mut self, delta: Vec2) {
+        let meters = self.meters_per_pixel();
+        self.center[0] -= delta.x as f64 * meters;
+        self.center[1] += delta.y as f64 * meters;
+    }
+
+    /// Zooms so a body of `radius` fills part of the screen, the first time it is called.
+    pub fn fit(&mut self, radius: f64) {
+        if self.fitted || radius <= 0. {
+            return;
+        }
+
+        let zoom = (screen_height() as f64 * INITIAL_FILL / (2. * radius))
+            .log10()
+            .clamp(MIN_ZOOM, MAX_ZOOM);
+        self.zoom = zoom;
+        self.target_zoom = zoom;
+        self.fitted = true;
+    }
+
+    /// Moves to `followed`, the position of what the camera follows if it can be found,
+    /// and eases the zoom. `dt` is the duration of the last frame, in seconds.
+    pub fn update(&mut self, followed: Option<Vector>, dt: f32) {
+        if self.follow != Follow::Free {
+            if let Some(followed) = followed {
+                self.center = followed;
+            }
+        }
+
+        let anchored = self.anchor.map(|anchor| (anchor, self.to_world(anchor)));
+
+        let ease = 1. - (-ZOOM_SPEED * dt as f64).exp();
+        self.zoom += (self.target_zoom - self.zoom) * ease;
+        if (self.target_zoom - self.zoom).abs() < 1e-4 {
+            self.zoom = self.target_zoom;
+            self.anchor = None;
+        }
+
+        // Keeps the point under the anchor where it was.
+        if let Some((anchor, world)) = anchored {
+            let moved = self.to_world(anchor);
+            self.center[0] += world[0] - moved[0];
+            self.center[1] += world[1] - moved[1];
+        }
+    }
+
+    /// Pixels per meter.
+    pub fn scale(&self) -> f64 {
+        10f64.powf(self.zoom)
+    }
+
+    pub fn meters_per_pixel(&self) -> f64 {
+        1. / self.scale()
+    }
+
+    /// The length of `meters` on screen, in pixels.
+    pub fn pixels(&self, meters: f64) -> f32 {
+        (meters * self.scale()) as f32
+    }
+
+    /// Where a world position is on screen, in pixels.
+    /// The world's y axis points up, the screen's down.
+    pub fn to_screen(&self, world: Vector) -> Vec2 {
+        let scale = self.scale();
+        vec2(
+            (screen_width() as f64 / 2. + (world[0] - self.center[0]) * scale) as f32,
+            (screen_height() as f64 / 2. - (world[1] - self.center[1]) * scale) as f32,
+        )
+    }
+
+    /// The world position under a screen position, in pixels.
+    pub fn to_world(&self, screen: Vec2) -> Vector {
+        let meters = self.meters_per_pixel();
+        [
+            self.center[0] + (screen.x - screen_width() / 2.) as f64 * meters,
+            self.center[1] - (screen.y - screen_height() / 2.) as f64 * meters,
+        ]
+    }
+
+    /// Whether a circle of `radius` pixels around `screen` shows at least partly.
+    pub fn is_visible(&self, screen: Vec2, radius: f32) -> bool {
+        screen.x + radius >= 0.
+            && screen.x - radius <= screen_width()
+            && screen.y + radius >= 0.
+            && screen.y - radius <= screen_height()
+    }
+}
@@ -0,0 +1,53 @@
+/// Formats a distance with a unit suited to its magnitude, such as `12.3 km`.
+pub fn distance(meters: f64) -> String {
+    let magnitude = meters.abs();
+    if magnitude >= 1e9 {
+        format!("{:.2} Gm", meters / 1e9)
+    } else if magnitude >= 1e6 {
+        format!("{:.2} Mm", meters / 1e6)
+    } else if magnitude >= 1e3 {
+        format!("{:.2} km", meters / 1e3)
+    } else {
+        format!("{:.1} m", meters)
+    }
+}
+
+/// Formats a speed with a unit suited to its magnitude, such as `3.20 km/s`.
+pub fn speed(meters_per_second: f64) -> String {
+    if meters_per_second.abs() >= 1e3 {
+        format!("{:.2} km/s", meters_per_second / 1e3)
+    } else {
+        format!("{:.2} m/s", meters_per_second)
+    }
+}
+
+/// Formats a mass in kilograms, or tonnes when large enough.
+pub fn mass(kilograms: f64) -> String {
+    if kilograms.abs() >= 1e3 {
+        format!("{:.1} t", kilograms / 1e3)
+    } else {
+        format!("{:.0} kg", kilograms)
+    }
+}
+
+/// Formats a duration, such as `2d 03h`, `1h 20m` or `45.0s`.
+/// Only the two largest units are shown.
+pub fn duration(seconds: f64) -> String {
+    let total = seconds.abs().round() as u64;
+    let (days, hours, minutes, secs) = (
+        total / 86_400,
+        total / 3_600 % 24,
+        total / 60 % 60,
+        total % 60,
+    );
+
+    if days > 0 {
+        format!("{}d {:02}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {:02}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, secs)
+    } else {
+        format!("{:.1}s", seconds)
+    }
+}
@@ -0,0 +1,183 @@
+use common::{
+    data::{
+        inputs::inputs_for,
+        object::{Object, Planet, Ship},
+        state::State,
+    },
+    orbit::{
+        gravity::gravitational_parameter, relative::Relative, transfer::closest_approach,
+    },
+};
+use macroquad::prelude::*;
+
+use super::{camera::Follow, format};
+
+const FONT_SIZE: f32 = 20.;
+const LINE_HEIGHT: f32 = 22.;
+const MARGIN: f32 = 12.;
+
+/// Offset of the values from the labels, in pixels.
+const VALUE_OFFSET: f32 = 130.;
+
+const LABEL_COLOR: Color = GRAY;
+const VALUE_COLOR: Color = WHITE;
+
+/// Draws the flight data of `ship` in the top left corner, relative to `reference`,
+/// and how it closes in on `target`.
+/// Must be called with the default camera.
+pub fn draw(
+    state: &State,
+    ship: Option<&Ship>,
+    reference: Option<&Planet>,
+    target: Option<&Object>,
+) {
+    let mut lines = vec![(
+        "Warp",
+        state
+            .clock
+            .warp_factor()
+            .map_or("-".to_owned(), |warp| format!("x{}", warp)),
+    )];
+
+    if let Some(ship) = ship {
+        lines.extend(ship_lines(state, ship, reference));
+        if let Some(target) = target {
+            lines.extend(target_lines(ship, reference, target));
+        }
+    } else {
+        lines.push(("Ship", "none".to_owned()));
+    }
+
+    for (i, (label, value)) in lines.iter().enumerate() {
+        let y = MARGIN + FONT_SIZE + i as f32 * LINE_HEIGHT;
+        draw_text(label, MARGIN, y, FONT_SIZE, LABEL_COLOR);
+        draw_text(value, MARGIN + VALUE_OFFSET, y, FONT_SIZE, VALUE_COLOR);
+    }
+
+    let fps = format!("{} FPS", get_fps());
+    let size = measure_text(&fps, None, FONT_SIZE as u16, 1.);
+    draw_text(
+        &fps,
+        screen_width() - size.width - MARGIN,
+        MARGIN + FONT_SIZE,
+        FONT_SIZE,
+        LABEL_COLOR,
+    );
+}
+
+/// Draws what the camera follows, and the scale, in the bottom left corner.
+/// Must be called with the default camera.
+pub fn draw_view(follow: Follow, meters_per_pixel: f64) {
+    let text = format!(
+        "Camera: {}, 1 px = {}",
+        follow,
+        format::distance(meters_per_pixel)
+    );
+    draw_text(&text, MARGIN, screen_height() - MARGIN, FONT_SIZE, LABEL_COLOR);
+}
+
+fn ship_lines(
+    state: &State,
+    ship: &Ship,
+    reference: Option<&Planet>,
+) -> Vec<(&'static str, String)> {
+    let inputs = inputs_for(&state.inputs, ship.object.id);
+
+    let mut lines = vec![
+        ("Ship", ship.object.label()),
+        ("Nav mode", format!("{:?}", inputs.navmode)),
+        ("Throttle", format!("{:.0} %", inputs.throttle * 100.)),
+        (
+            "Fuel",
+            format!(
+                "{} ({})",
+                format::mass(ship.fuel_mass()),
+                format::speed(ship.delta_v())
+            ),
+        ),
+    ];
+
+    let Some(reference) = reference else {
+        return lines;
+    };
+
+    let relative = Relative::between(&ship.object, &reference.object);
+    lines.extend([
+        ("Reference", reference.object.label()),
+        ("Speed", format::speed(relative.speed())),
+        (
+            "Altitude",
+            format::distance(relative.distance() - reference.radius),
+        ),
+        ("Vert. speed", format::speed(relative.vertical_speed())),
+    ]);
+
+    let Some(elements) = relative.elements(gravitational_parameter(reference.object.mass)) else {
+        return lines;
+    };
+
+    let altitude = |radius: f64| format::distance(radius - reference.radius);
+    lines.extend([
+        (
+            "Semi-major",
+            if elements.is_bound() {
+                format::distance(elements.semi_major_axis)
+            } else {
+                "-".to_owned()
+            },
+        ),
+        ("Eccentricity", format!("{:.4}", elements.eccentricity)),
+        ("Periapsis", altitude(elements.periapsis())),
+        (
+            "Time to Pe",
+            elements
+                .time_to_periapsis()
+                .map_or("-".to_owned(), format::duration),
+        ),
+        (
+            "Apoapsis",
+            elements.apoapsis().map_or("-".to_owned(), altitude),
+        ),
+        (
+            "Period",
+            elements.period().map_or("-".to_owned(), format::duration),
+        ),
+    ]);
+
+    lines
+}
+
+fn target_lines(
+    ship: &Ship,
+    reference: Option<&Planet>,
+    target: &Object,
+) -> Vec<(&'static str, String)> {
+    let relative = Relative::between(&ship.object, target);
+
+    let mut lines = vec![
+        ("Target", target.label()),
+        ("Distance", format::distance(relative.distance())),
+        ("Rel. speed", format::speed(relative.speed())),
+        ("Closing", format::speed(-relative.vertical_speed())),
+    ];
+
+    // Both conics around the reference tell when they will be closest.
+    let approach = reference.and_then(|reference| {
+        let mu = gravitational_parameter(reference.object.mass);
+        let ship = Relative::between(&ship.object, &reference.object).elements(mu)?;
+        let target = Relative::between(target, &reference.object).elements(mu)?;
+        let horizon = ship
+            .period()
+            .unwrap_or(10. * relative.distance() / relative.speed().max(1e-9));
+        Some(closest_approach(&ship, &target, horizon))
+    });
+
+    lines.push((
+        "Closest",
+        approach.map_or("-".to_owned(), |(time, distance)| {
+            format!("{} in {}", format::distance(distance), format::duration(time))
+        }),
+    ));
+
+    lines
+}
@@ -0,0 +1,53 @@
+use macroquad::prelude::*;
+
+const FONT_SIZE: f32 = 16.;
+
+/// Space between a label and what it names, in pixels.
+const GAP: f32 = 4.;
+
+/// Text naming a point on screen, drawn only if there is room for it.
+pub struct Label {
+    /// The point named, in pixels.
+    pub anchor: Vec2,
+
+    /// How far from the anchor the text starts, in pixels, to clear what is drawn there.
+    pub clearance: f32,
+    pub text: String,
+    pub color: Color,
+}
+
+/// Draws `labels` in order, leaving out those that would overlap one already drawn.
+/// The most important labels should come first.
+///
+/// Each label is tried to the right of its anchor, then to the left, above and below.
+/// Must be called with the default camera.
+pub fn draw(labels: &[Label]) {
+    let screen = Rect::new(0., 0., screen_width(), screen_height());
+    let mut placed: Vec<Rect> = Vec::new();
+
+    for label in labels {
+        let size = measure_text(&label.text, None, FONT_SIZE as u16, 1.);
+        let offset = label.clearance + GAP;
+        let (width, height) = (size.width, size.height);
+
+        let candidates = [
+            vec2(offset, -height / 2.),
+            vec2(-offset - width, -height / 2.),
+            vec2(-width / 2., -offset - height),
+            vec2(-width / 2., offset),
+        ];
+
+        let free = candidates
+            .into_iter()
+            .map(|corner| {
+                let corner = label.anchor + corner;
+                Rect::new(corner.x, corner.y, width, height)
+            })
+            .find(|rect| rect.overlaps(&screen) && !placed.iter().any(|other| other.overlaps(rect)));
+
+        if let Some(rect) = free {
+            draw_text(&label.text, rect.x, rect.y + size.offset_y, FONT_SIZE, label.color);
+            placed.push(rect);
+        }
+    }
+}
@@ -1,3 +1,7 @@
+pub mod camera;
+pub mod format;
+pub mod hud;
 pub mod icon;
+pub mod labels;
 pub mod renderer;
-pub mod textures;
+pub mod textures;
\ No newline at end of file
@@ -1,79 +1,376 @@
-use common::data::state::State;
-use macroquad::experimental::camera::mouse::Camera;
+use std::{collections::HashSet, time::Duration};
+
+use common::{
+    data::{
+        inputs::inputs_for,
+        object::{Object, Ship},
+        state::State,
+    },
+    orbit::{norm, position_of, Vector},
+};
 use macroquad::prelude::*;
 
-use crate::data::client_state::ClientState;
+use crate::data::{
+    client_state::ClientState,
+    trajectory::{self, Trajectory},
+};
+
+use super::{
+    camera::{Camera, Follow},
+    format, hud,
+    labels::{self, Label},
+    textures::Textures,
+};
+
+/// Length of the velocity and acceleration arrows per decade of magnitude, in pixels.
+const ARROW_SCALE: f32 = 25.;
+
+/// Color of the velocity arrows.
+const VELOCITY_COLOR: Color = GREEN;
+
+/// Color of the acceleration arrows.
+const ACCELERATION_COLOR: Color = RED;
+
+/// Color of the engine plume, at its base.
+const PLUME_COLOR: Color = Color::new(1.0, 0.6, 0.2, 0.9);
+
+/// Color of the predicted trajectory.
+const TRAJECTORY_COLOR: Color = Color::new(0.4, 0.8, 1.0, 0.8);
 
-use super::textures::Textures;
+/// Color of the predicted trajectory when it ends on the surface.
+const IMPACT_COLOR: Color = Color::new(1.0, 0.3, 0.3, 0.8);
+
+/// How far from a body a click still selects it, in pixels.
+const PICK_DISTANCE: f32 = 12.;
+
+/// Bodies smaller than this on screen are drawn as a marker of this radius, in pixels,
+/// so they don't vanish when zoomed out.
+const MARKER_RADIUS: f32 = 4.;
+
+/// Color of planet markers and names.
+const PLANET_COLOR: Color = Color::new(0.6, 0.75, 1.0, 1.0);
+
+/// Color of ship markers and names.
+const SHIP_COLOR: Color = LIGHTGRAY;
+
+/// Color of the ring around the target, and of its name.
+const TARGET_COLOR: Color = Color::new(1.0, 0.85, 0.2, 1.0);
 
 pub struct Renderer<'a> {
     camera: Camera,
-    client_state: &'a ClientState,
     textures: &'a Textures,
+
+    /// Whether velocity and acceleration arrows are drawn over ships.
+    show_vectors: bool,
+
+    /// Ship textures found missing, so each is only reported once.
+    missing_ships: HashSet<String>,
+
+    /// Planet textures found missing, so each is only reported once.
+    missing_planets: HashSet<String>,
 }
 
 impl<'a> Renderer<'a> {
-    pub fn new(client_state: &'a ClientState, textures: &'a Textures) -> Self {
+    pub fn new(textures: &'a Textures) -> Self {
         Self {
             camera: Camera::default(),
-            client_state,
             textures,
+            show_vectors: false,
+            missing_ships: HashSet::new(),
+            missing_planets: HashSet::new(),
         }
     }
 
-    pub fn render(&mut self, state: &State) {
-        clear_background(BLACK);
+    /// Shows or hides the velocity and acceleration arrows.
+    pub fn toggle_vectors(&mut self) {
+        self.show_vectors = !self.show_vectors;
+    }
 
-        let mut objects = state.objects();
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
 
-        let target_object_pos = objects.position(|p| p.id == self.client_state.center);
+    /// The body drawn under `screen`, a position in pixels, if any.
+    /// Bodies too small to click are picked within a few pixels.
+    pub fn pick(&self, state: &State, screen: Vec2) -> Option<i32> {
+        let radii = state
+            .planets
+            .iter()
+            .map(|p| (&p.object, p.radius))
+            .chain(state.ships.iter().map(|s| (&s.object, s.radius())));
 
-        let target_planet = if let Some(pos) = target_object_pos {
-            objects.nth(pos)
-        } else {
-            None
-        };
-
-        let target = if let Some(t) = target_planet {
-            vec2(t.position[0] as f32, t.position[1] as f32)
-        } else {
-            vec2(0.0, 0.0)
-        };
+        radii
+            .map(|(object, radius)| {
+                let distance = self.camera.to_screen(position_of(object)).distance(screen);
+                (object.id, distance - self.camera.pixels(radius).max(PICK_DISTANCE))
+            })
+            .filter(|(_, outside)| *outside <= 0.)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(id, _)| id)
+    }
 
-        let wheel_val = mouse_wheel().1;
-        let mouse_pos: Vec2 = mouse_position_local().into();
+    /// Draws a frame. `dt` is the duration of the last one, in seconds.
+    pub fn render(&mut self, state: &State, client_state: &ClientState, dt: f32) {
+        clear_background(BLACK);
+        set_default_camera();
 
-        self.camera.scale_wheel(mouse_pos, wheel_val, 1.1);
-        self.camera.update(
-            mouse_pos,
-            is_mouse_button_down(MouseButton::Left),
-        );
+        let reference = client_state.reference_planet(state);
+        let ship = client_state.followed_ship(state);
 
-        let mut camera: Camera2D = (&self.camera).into();
-        camera.target = target;
+        if let Some(planet) = reference {
+            self.camera.fit(planet.radius);
+        }
+        let followed = match self.camera.follow() {
+            Follow::Reference => reference.map(|planet| position_of(&planet.object)),
+            Follow::Ship => ship.map(|ship| position_of(&ship.object)),
+            Follow::Free => None,
+        };
+        self.camera.update(followed, dt);
 
-        set_camera(&camera);
+        // Arrows show motion relative to the reference.
+        let frame = reference.map(|planet| &planet.object).map(|t| {
+            (
+                vec2(t.velocity[0] as f32, t.velocity[1] as f32),
+                vec2(t.acceleration[0] as f32, t.acceleration[1] as f32),
+            )
+        });
 
         for planet in state.planets.iter() {
-            let texture = self.textures.planets.get(&planet.object.texture);
-            if texture.is_none() {
-                log::error!("Texture {} not found", planet.object.texture);
+            let position = self.camera.to_screen(position_of(&planet.object));
+            let radius = self.camera.pixels(planet.radius);
+            if !self.camera.is_visible(position, radius) {
                 continue;
             }
 
-            draw_texture_ex(
-                *texture.unwrap(),
-                planet.object.position[0] as f32 - planet.radius as f32,
-                planet.object.position[1] as f32 - planet.radius as f32,
+            if radius < MARKER_RADIUS {
+                draw_circle(position.x, position.y, MARKER_RADIUS, PLANET_COLOR);
+                continue;
+            }
+
+            match self.textures.planets.get(&planet.object.texture) {
+                Some(texture) => draw_texture_ex(
+                    *texture,
+                    position.x - radius,
+                    position.y - radius,
+                    WHITE,
+                    DrawTextureParams {
+                        dest_size: Some(vec2(radius * 2., radius * 2.)),
+                        ..Default::default()
+                    },
+                ),
+                None => {
+                    if self.missing_planets.insert(planet.object.texture.clone()) {
+                        log::warn!("Planet texture {} not found", planet.object.texture);
+                    }
+
+                    draw_circle(position.x, position.y, radius, PLANET_COLOR);
+                }
+            }
+        }
+
+        let trajectory = ship
+            .zip(reference)
+            .and_then(|(ship, reference)| trajectory::predict(state, ship, reference));
+        if let Some(trajectory) = &trajectory {
+            draw_trajectory(&self.camera, state, trajectory);
+        }
+
+        for ship in state.ships.iter() {
+            let throttle = inputs_for(&state.inputs, ship.object.id).throttle;
+            self.draw_ship(ship, throttle);
+        }
+
+        if let Some(target) = client_state.target_object(state) {
+            self.draw_target_ring(state, target);
+        }
+
+        if self.show_vectors {
+            let (frame_velocity, frame_acceleration) = frame.unwrap_or_default();
+
+            for ship in state.ships.iter() {
+                let position = self.camera.to_screen(position_of(&ship.object));
+                let velocity = vec2(ship.object.velocity[0] as f32, ship.object.velocity[1] as f32);
+                let acceleration = vec2(
+                    ship.object.acceleration[0] as f32,
+                    ship.object.acceleration[1] as f32,
+                );
+
+                draw_vector(position, velocity - frame_velocity, VELOCITY_COLOR);
+                draw_vector(position, acceleration - frame_acceleration, ACCELERATION_COLOR);
+            }
+        }
+
+        labels::draw(&self.labels(state, client_state, trajectory.as_ref()));
+
+        hud::draw(state, ship, reference, client_state.target_object(state));
+        hud::draw_view(self.camera.follow(), self.camera.meters_per_pixel());
+    }
+
+    /// Draws a ship's sprite rotated to its heading, with its engine plume behind it.
+    /// Ships without a texture are drawn as a triangle.
+    fn draw_ship(&mut self, ship: &Ship, throttle: f32) {
+        let position = self.camera.to_screen(position_of(&ship.object));
+        let radius = self.camera.pixels(ship.radius());
+        if !self.camera.is_visible(position, radius * 4.) {
+            return;
+        }
+
+        // Headings are counterclockwise with y up, the screen has y down.
+        let heading = ship.object.heading as f32;
+        let forward = vec2(heading.cos(), -heading.sin());
+        let side = forward.perp();
+
+        if radius < MARKER_RADIUS {
+            let size = MARKER_RADIUS * 1.5;
+            draw_triangle(
+                position + forward * size,
+                position - forward * size + side * size * 0.7,
+                position - forward * size - side * size * 0.7,
+                SHIP_COLOR,
+            );
+            return;
+        }
+
+        if throttle > 0. && ship.fuel_mass() > 0. {
+            let base = position - forward * radius * 0.8;
+            let tip = base - forward * radius * (0.5 + 2.5 * throttle);
+            draw_triangle(
+                base + side * radius * 0.4,
+                base - side * radius * 0.4,
+                tip,
+                PLUME_COLOR,
+            );
+        }
+
+        match self.textures.ships.get(&ship.object.texture) {
+            Some(texture) => draw_texture_ex(
+                *texture,
+                position.x - radius,
+                position.y - radius,
                 WHITE,
                 DrawTextureParams {
-                    dest_size: Some(vec2(planet.radius as f32 * 2., planet.radius as f32 * 2.)),
+                    dest_size: Some(vec2(radius * 2., radius * 2.)),
+                    rotation: -heading,
                     ..Default::default()
                 },
-            );
+            ),
+            None => {
+                if self.missing_ships.insert(ship.object.texture.clone()) {
+                    log::warn!("Ship texture {} not found", ship.object.texture);
+                }
+
+                draw_triangle(
+                    position + forward * radius,
+                    position - forward * radius + side * radius * 0.7,
+                    position - forward * radius - side * radius * 0.7,
+                    SHIP_COLOR,
+                );
+            }
+        }
+    }
+
+    /// Circles the target, however small it is on screen.
+    fn draw_target_ring(&self, state: &State, target: &Object) {
+        let position = self.camera.to_screen(position_of(target));
+        let radius = self.drawn_radius(state, target) + 4.;
+        if self.camera.is_visible(position, radius) {
+            draw_circle_lines(position.x, position.y, radius, 1.5, TARGET_COLOR);
         }
+    }
+
+    /// The radius a body is drawn with, in pixels, marker included.
+    fn drawn_radius(&self, state: &State, object: &Object) -> f32 {
+        let radius = state
+            .planets
+            .iter()
+            .find(|p| p.object.id == object.id)
+            .map(|p| p.radius)
+            .or_else(|| state.ships.iter().find(|s| s.object.id == object.id).map(|s| s.radius()))
+            .unwrap_or(0.);
 
-        println!("FPS: {}", get_fps());
+        self.camera.pixels(radius).max(MARKER_RADIUS * 1.5)
+    }
+
+    /// Names every body on screen, and the apsides of the trajectory.
+    /// The followed ship, the target and the reference come first, so they keep their labels
+    /// when others are in the way. Larger bodies come before smaller ones.
+    fn labels(
+        &self,
+        state: &State,
+        client_state: &ClientState,
+        trajectory: Option<&Trajectory>,
+    ) -> Vec<Label> {
+        let followed = client_state.followed_ship(state).map(|s| s.object.id);
+        let reference = client_state.reference_planet(state).map(|p| p.object.id);
+
+        let planets = state.planets.iter().map(|p| (&p.object, PLANET_COLOR, 4));
+        let ships = state.ships.iter().map(|s| (&s.object, SHIP_COLOR, 5));
+
+        let mut labels: Vec<(u8, Label)> = planets
+            .chain(ships)
+            .filter_map(|(object, color, rank)| {
+                let anchor = self.camera.to_screen(position_of(object));
+                if !self.camera.is_visible(anchor, 0.) {
+                    return None;
+                }
+
+                let (rank, color) = if Some(object.id) == followed {
+                    (0, color)
+                } else if Some(object.id) == client_state.target {
+                    (1, TARGET_COLOR)
+                } else if Some(object.id) == reference {
+                    (2, color)
+                } else {
+                    (rank, color)
+                };
+
+                let label = Label {
+                    anchor,
+                    clearance: self.drawn_radius(state, object),
+                    text: object.label(),
+                    color,
+                };
+                Some((rank, label))
+            })
+            .collect();
+
+        if let Some(trajectory) = trajectory {
+            labels.extend(apsis_labels(state, trajectory).into_iter().map(|(position, text)| {
+                let label = Label {
+                    anchor: self.camera.to_screen(position),
+                    clearance: 4.,
+                    text,
+                    color: TRAJECTORY_COLOR,
+                };
+                (3, label)
+            }));
+        }
+
+        labels.sort_by(|(a, x), (b, y)| a.cmp(b).then(y.clearance.total_cmp(&x.clearance)));
+        labels.into_iter().map(|(_, label)| label).collect()
+    }
+
+    /// Draws the "reconnecting" overlay, at the bottom of the screen.
+    pub fn draw_reconnecting(&self, attempt: u32, retry_in: Duration) {
+        let text = if retry_in.is_zero() {
+            format!("Reconnecting... (attempt {})", attempt)
+        } else {
+            format!(
+                "Connection lost. Reconnecting in {:.0}s... (attempt {})",
+                retry_in.as_secs_f32().ceil(),
+                attempt
+            )
+        };
+
+        let font_size = 24;
+        let size = measure_text(&text, None, font_size, 1.0);
+        draw_text(
+            &text,
+            screen_width() / 2.0 - size.width / 2.0,
+            screen_height() - 2.0 * size.height,
+            font_size as f32,
+            WHITE,
+        );
     }
 
     pub fn draw_splash(&self, splash_texture: Texture2D) {
@@ -85,3 +382,85 @@ impl<'a> Renderer<'a> {
         );
     }
 }
+
+/// Draws an arrow from `origin`, in pixels, along `vector`, in world units.
+/// Its length grows with the logarithm of the magnitude,
+/// so both slow drifts and orbital speeds fit on screen.
+fn draw_vector(origin: Vec2, vector: Vec2, color: Color) {
+    let magnitude = vector.length();
+    if magnitude < 1e-3 {
+        return;
+    }
+
+    // The screen has y down.
+    let direction = vec2(vector.x, -vector.y) / magnitude;
+    let length = ARROW_SCALE * (1. + magnitude).log10().max(0.5);
+    let tip = origin + direction * length;
+    let head = 8.;
+
+    draw_line(origin.x, origin.y, tip.x, tip.y, 2., color);
+    draw_triangle(
+        tip + direction * head,
+        tip + direction.perp() * head * 0.5,
+        tip - direction.perp() * head * 0.5,
+        color,
+    );
+}
+
+/// Finds where a point relative to a body is, and how far above the body's surface it is.
+fn relative_point(state: &State, reference: i32, point: Vector) -> Option<(Vector, f64)> {
+    let planet = state.planets.iter().find(|p| p.object.id == reference)?;
+    let origin = position_of(&planet.object);
+    let position = [origin[0] + point[0], origin[1] + point[1]];
+    Some((position, norm(point) - planet.radius))
+}
+
+/// Draws the path ahead, and marks its apsides.
+fn draw_trajectory(camera: &Camera, state: &State, trajectory: &Trajectory) {
+    let color = if trajectory.impact {
+        IMPACT_COLOR
+    } else {
+        TRAJECTORY_COLOR
+    };
+
+    let points: Vec<Vec2> = trajectory
+        .points
+        .iter()
+        .filter_map(|point| relative_point(state, trajectory.reference, *point))
+        .map(|(position, _)| camera.to_screen(position))
+        .collect();
+
+    let (width, height) = (screen_width(), screen_height());
+    for segment in points.windows(2) {
+        let (a, b) = (segment[0], segment[1]);
+
+        // Segments entirely to one side of the screen can't show.
+        if (a.x < 0. && b.x < 0.)
+            || (a.x > width && b.x > width)
+            || (a.y < 0. && b.y < 0.)
+            || (a.y > height && b.y > height)
+        {
+            continue;
+        }
+
+        draw_line(a.x, a.y, b.x, b.y, 1.5, color);
+    }
+
+    for apsis in [trajectory.periapsis, trajectory.apoapsis].into_iter().flatten() {
+        if let Some((position, _)) = relative_point(state, trajectory.reference, apsis) {
+            let position = camera.to_screen(position);
+            draw_circle(position.x, position.y, 4., color);
+        }
+    }
+}
+
+/// The labels of the apsides, with the altitude above the reference's surface.
+fn apsis_labels(state: &State, trajectory: &Trajectory) -> Vec<(Vector, String)> {
+    [("Pe", trajectory.periapsis), ("Ap", trajectory.apoapsis)]
+        .into_iter()
+        .filter_map(|(name, apsis)| {
+            let (position, altitude) = relative_point(state, trajectory.reference, apsis?)?;
+            Some((position, format!("{} {}", name, format::distance(altitude))))
+        })
+        .collect()
+}
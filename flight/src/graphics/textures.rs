@@ -1,4 +1,4 @@
-use std::{collections::HashMap, env::current_exe, fs, path::PathBuf};
+use std::{collections::HashMap, env::current_exe, fs, path::PathBuf, sync::OnceLock};
 
 use macroquad::{
     prelude::FileError,
@@ -7,23 +7,39 @@ use macroquad::{
 
 const TEXTURE_PATHS: [&str; 1] = ["ui/splash.png"];
 
+/// Asset directory set from the command line, used instead of the one next to the executable.
+static ASSET_DIR: OnceLock<PathBuf> = OnceLock::new();
+
 pub struct Textures {
     pub splash: Texture2D,
     pub planets: HashMap<String, Texture2D>,
+    pub ships: HashMap<String, Texture2D>,
 }
 
 impl Textures {
+    /// Loads assets from `path` instead of the directory next to the executable.
+    /// Must be called before any asset is loaded.
+    pub fn set_asset_dir(path: PathBuf) {
+        if ASSET_DIR.set(path).is_err() {
+            log::warn!("The asset directory was already set");
+        }
+    }
+
     pub fn get_asset_base_path(package: &str) -> PathBuf {
+        if let Some(dir) = ASSET_DIR.get() {
+            return dir.clone();
+        }
+
         let mut path = PathBuf::new();
         if let Ok(exe_path) = current_exe() {
             if let Some(exe_dir) = exe_path.parent() {
-                path.push(exe_dir.to_path_buf());
+                path.push(exe_dir);
             }
         }
         path.push(package);
         path.push("assets");
 
-        return path;
+        path
     }
 
     /// Finds the path to a texture, based on the current executable's path.
@@ -32,9 +48,9 @@ impl Textures {
         path.push(name);
 
         if let Some(path_str) = path.to_str() {
-            return path_str.to_string();
+            path_str.to_string()
         } else {
-            return format!("{package}/assets/{name}");
+            format!("{package}/assets/{name}")
         }
     }
 
@@ -54,50 +70,58 @@ impl Textures {
         Ok(textures)
     }
 
-    async fn load_planets() -> HashMap<String, Texture2D> {
-        let mut planets: HashMap<String, Texture2D> = HashMap::new();
+    /// Loads every texture in an asset subdirectory, keyed by file name without extension.
+    async fn load_directory(name: &str) -> HashMap<String, Texture2D> {
+        let mut textures: HashMap<String, Texture2D> = HashMap::new();
         let mut base = Self::get_asset_base_path("flight");
-        base.push("planets");
+        base.push(name);
         let dir = fs::read_dir(&base);
         if let Ok(dir) = dir {
-            for planet_name in dir {
-                if planet_name.is_err() {
-                    log::warn!(
-                        "Failed to read planet directory: {}",
-                        planet_name.unwrap_err()
-                    );
-                    continue;
-                }
-
-                let planet_path = planet_name.unwrap().path();
-                let planet_name = planet_path.to_str();
-                if planet_name.is_none() {
-                    log::warn!("Failed to read planet directory: path is not valid UTF-8.");
+            for entry in dir {
+                let path = match entry {
+                    Ok(entry) => entry.path(),
+                    Err(err) => {
+                        log::warn!("Failed to read {} directory: {}", name, err);
+                        continue;
+                    }
+                };
+                let path_str = path.to_str();
+                if path_str.is_none() {
+                    log::warn!("Failed to read {} directory: path is not valid UTF-8.", name);
                     continue;
                 }
 
-                let texture = load_texture(&planet_name.unwrap()).await;
-                if texture.is_err() {
-                    log::warn!("Failed to load planet texture: {}", texture.unwrap_err());
-                    continue;
-                }
+                let texture = match load_texture(path_str.unwrap()).await {
+                    Ok(texture) => texture,
+                    Err(err) => {
+                        log::warn!("Failed to load {} texture: {}", name, err);
+                        continue;
+                    }
+                };
 
-                let file_stem = planet_path.file_stem();
+                let file_stem = path.file_stem();
                 if file_stem.is_none() {
-                    log::warn!("Failed to load planet texture: path has no file stem.");
+                    log::warn!("Failed to load {} texture: path has no file stem.", name);
                     continue;
                 }
 
-                let texture = texture.unwrap();
-
                 let file_stem = file_stem.unwrap().to_str().unwrap();
-                planets.insert(file_stem.to_string(), texture);
+                textures.insert(file_stem.to_string(), texture);
             }
         }
 
-        println!("{:?}", planets);
+        log::debug!("Loaded {} textures: {:?}", name, textures);
+
+        textures
+    }
 
-        planets
+    async fn load_planets() -> HashMap<String, Texture2D> {
+        Self::load_directory("planets").await
+    }
+
+    /// Ship sprites should point along +x, the direction of a heading of zero.
+    async fn load_ships() -> HashMap<String, Texture2D> {
+        Self::load_directory("ships").await
     }
 
     pub async fn new() -> Self {
@@ -113,8 +137,13 @@ impl Textures {
                 };
 
                 let planets = Self::load_planets().await;
+                let ships = Self::load_ships().await;
 
-                Self { splash, planets }
+                Self {
+                    splash,
+                    planets,
+                    ships,
+                }
             }
             Err(err) => {
                 log::error!("Failed to load textures: {}", err);
@@ -1,4 +1,5 @@
 mod cli;
+mod controls;
 mod data;
 mod graphics;
 mod networking;
@@ -6,21 +7,28 @@ mod networking;
 use std::{
     sync::{mpsc::channel, Arc, RwLock},
     thread,
+    time::Instant,
 };
 
-use common::data::state::State;
-use data::client_state::ClientState;
+use cli::arg_parser::arguments;
+use controls::{
+    bindings::{load_bindings, Action},
+    controller::Controller,
+};
+use data::{client_state::ClientState, snapshots::SnapshotBuffer};
 use env_logger::Env;
 use macroquad::{miniquad::conf::Icon, prelude::*};
 use networking::client::{Client, Config, NetThreadEvent};
 use graphics::{icon::*, textures::Textures, renderer::Renderer};
 
 fn config() -> Conf {
+    let arguments = arguments();
+
     Conf {
         window_title: "Flight".to_owned(),
-        window_width: 1280,
-        window_height: 720,
-        fullscreen: false,
+        window_width: arguments.width,
+        window_height: arguments.height,
+        fullscreen: arguments.fullscreen,
         window_resizable: true,
         icon: Some(Icon {
             big: ICON_BIG,
@@ -39,11 +47,20 @@ async fn main() {
 
     env_logger::init_from_env(env);
 
-    let state_lock: Arc<RwLock<Option<State>>> = Arc::new(RwLock::new(None));
+    let state_lock = Arc::new(RwLock::new(SnapshotBuffer::new()));
+
+    let arguments = arguments();
+    if let Some(assets) = &arguments.assets {
+        Textures::set_asset_dir(assets.clone());
+    }
 
     let client_config = Config {
-        network_interface: "127.0.0.1",
-        network_port: 5000,
+        host: arguments.host.clone(),
+        port: arguments.port,
+        transport: arguments.transport,
+        name: arguments.callsign.clone(),
+        role: arguments.role,
+        ship: arguments.ship,
     };
 
     let (events_tx, events_rx) = channel();
@@ -58,6 +75,8 @@ async fn main() {
 
     let client = client.unwrap();
 
+    let mut controller = Controller::new(load_bindings(&arguments.bindings), client.commands());
+
     thread::spawn(move || {
         log::trace!("Network thread starting...");
 
@@ -66,9 +85,12 @@ async fn main() {
 
     let mut connected = false;
 
-    let client_state = ClientState { center: 1 };
+    // The current reconnection attempt, and when the next one starts.
+    let mut reconnecting: Option<(u32, Instant)> = None;
 
-    let mut renderer = Renderer::new(&client_state, &textures);
+    let mut client_state = ClientState::default();
+
+    let mut renderer = Renderer::new(&textures);
 
     'outer: loop {
         'inner: loop {
@@ -82,28 +104,38 @@ async fn main() {
                     NetThreadEvent::Connected => {
                         log::info!("Connected to server");
                         connected = true;
+                        reconnecting = None;
                     }
                     NetThreadEvent::Disconnected => {
                         log::info!("Disconnected from server");
                         connected = false;
                     }
+                    NetThreadEvent::Reconnecting { attempt, delay } => {
+                        connected = false;
+                        reconnecting = Some((attempt, Instant::now() + delay));
+                    }
+                    NetThreadEvent::Claimed(ship) => client_state.ship = Some(ship),
                 },
             }
         }
         
         if !connected {
             clear_background(BLACK);
+            set_default_camera();
             
             // Draw splash screen
             renderer.draw_splash(textures.splash);
 
+            if let Some((attempt, retry_at)) = reconnecting {
+                let retry_in = retry_at.saturating_duration_since(Instant::now());
+                renderer.draw_reconnecting(attempt, retry_in);
+            }
+
             next_frame().await;
             continue;
         }
 
-        let state_guard = state_lock.read().unwrap();
-        let state = state_guard.clone();
-        drop(state_guard);
+        let state = state_lock.write().unwrap().sample(Instant::now());
 
         if state.is_none() {
             next_frame().await;
@@ -112,7 +144,31 @@ async fn main() {
 
         let state = state.unwrap();
 
-        renderer.render(&state);
+        // A left click targets a body, a right click makes it the reference.
+        if let Some(position) = renderer.camera_mut().handle_mouse() {
+            if let Some(id) = renderer.pick(&state, position) {
+                controller.push(Action::SetTarget(id));
+            }
+        }
+        if is_mouse_button_pressed(MouseButton::Right) {
+            if let Some(id) = renderer.pick(&state, mouse_position().into()) {
+                controller.push(Action::SetReference(id));
+            }
+        }
+
+        let now = Instant::now();
+        let dt = get_frame_time();
+        for action in controller.update(&state, &mut client_state, now, dt) {
+            match action {
+                Action::ToggleVectors => renderer.toggle_vectors(),
+                Action::CycleCamera => renderer.camera_mut().cycle_follow(),
+                Action::ZoomIn => renderer.camera_mut().zoom_in(),
+                Action::ZoomOut => renderer.camera_mut().zoom_out(),
+                _ => {}
+            }
+        }
+
+        renderer.render(&state, &client_state, dt);
 
         next_frame().await;
     }
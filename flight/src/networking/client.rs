@@ -1,15 +1,36 @@
 use std::{
+    collections::VecDeque,
     net::SocketAddr,
     sync::{mpsc::Sender, Arc, RwLock},
+    time::{Duration, Instant},
 };
 
-use common::{data::state::State, messages::from_server::FromServerMessage};
+use common::{
+    data::state::State,
+    messages::{
+        delta::SNAPSHOT_HISTORY,
+        encoding::BincodeEncoder,
+        from_client::FromClientMessage,
+        from_server::FromServerMessage,
+        handshake::{peek_rejection, peek_version, Hello, Role, Welcome, PROTOCOL_VERSION},
+        transport::TransportKind,
+    },
+};
 use message_io::{
     network::{Endpoint, NetEvent, Transport},
     node::{self, NodeEvent, NodeHandler, NodeListener},
 };
 
-use crate::graphics::textures::Textures;
+use crate::{data::snapshots::SnapshotBuffer, graphics::textures::Textures};
+
+/// How often the request to stream snapshots over UDP is repeated, until snapshots arrive.
+const STREAM_RETRY: Duration = Duration::from_millis(500);
+
+/// The delay before the first reconnection attempt. It doubles after each failed attempt.
+const RECONNECT_DELAY_MIN: Duration = Duration::from_millis(500);
+
+/// The longest delay between two reconnection attempts.
+const RECONNECT_DELAY_MAX: Duration = Duration::from_secs(30);
 
 /// NetThreadEvents are events that are sent to the graphics thread.
 /// These are sent by a mpsc channel.
@@ -17,6 +38,12 @@ pub enum NetThreadEvent {
     Quit,
     Connected,
     Disconnected,
+
+    /// The connection was lost, and the next attempt to get it back is in `delay`.
+    Reconnecting { attempt: u32, delay: Duration },
+
+    /// The server gave us control of a ship.
+    Claimed(i32),
 }
 
 /// Signals are message-io's way of giving the network thread a way of telling
@@ -25,13 +52,46 @@ pub enum NetThreadEvent {
 /// More info: https://docs.rs/message-io/0.5.0/message_io/node/struct.NodeSignals.html
 enum Signal {
     Quit,
+
+    /// Asks the server to stream snapshots over UDP, unless they already arrive.
+    Stream,
+
+    /// Tries to connect to the server again.
+    Reconnect,
+
+    /// Sends a command from the graphics thread to the server.
+    Command(FromClientMessage),
+}
+
+/// Lets the graphics thread send commands to the server, through the network thread.
+#[derive(Clone)]
+pub struct Commands {
+    node: NodeHandler<Signal>,
+}
+
+impl Commands {
+    /// Queues a command. It is dropped if the client isn't connected when it is handled.
+    pub fn send(&self, message: FromClientMessage) {
+        self.node.signals().send(Signal::Command(message));
+    }
 }
 
 /// This is used to configure the network options of the client.
 /// Other options may be added in the future.
 pub struct Config {
-    pub network_interface: &'static str,
-    pub network_port: u16,
+    /// The host name or address of the server.
+    pub host: String,
+    pub port: u16,
+
+    /// How snapshots are received. Should match the server's transport.
+    pub transport: TransportKind,
+
+    /// The name the server knows this client by.
+    pub name: String,
+    pub role: Role,
+
+    /// The id of the ship to take control of once connected.
+    pub ship: Option<i32>,
 }
 
 /// The client is responsible for connecting to the server, and receiving updates from it.
@@ -40,12 +100,34 @@ pub struct Config {
 /// This is all done through message-io, a library which provides easy networking.
 pub struct Client {
     node: NodeHandler<Signal>,
-    listener: NodeListener<Signal>,
+    listener: Option<NodeListener<Signal>>,
     server_id: Endpoint,
     config: Config,
     local_addr: SocketAddr,
-    state_lock: Arc<RwLock<Option<State>>>,
+    state_lock: Arc<RwLock<SnapshotBuffer>>,
     events_tx: Sender<NetThreadEvent>,
+    encoder: BincodeEncoder,
+
+    /// The session the server gave us, once the handshake is done.
+    session: Option<u64>,
+
+    /// The secret used to resume our session after losing the connection.
+    token: Option<u64>,
+
+    /// The ships we control, claimed again after reconnecting.
+    claimed: Vec<i32>,
+
+    /// The number of reconnection attempts since the connection was lost.
+    attempt: u32,
+
+    /// Recently received snapshots, oldest first, with their tick.
+    snapshots: VecDeque<(u64, State)>,
+
+    /// The UDP endpoint snapshots are streamed from, when using the UDP transport.
+    stream: Option<Endpoint>,
+
+    /// Whether a snapshot came through the stream yet.
+    streaming: bool,
 }
 
 impl Client {
@@ -54,7 +136,7 @@ impl Client {
     /// Args:
     pub fn new(
         config: Config,
-        state_lock: Arc<RwLock<Option<State>>>,
+        state_lock: Arc<RwLock<SnapshotBuffer>>,
         events_tx: Sender<NetThreadEvent>,
     ) -> Option<Client> {
         let (node, listener) = node::split();
@@ -63,8 +145,8 @@ impl Client {
 
         log::info!(
             "Client is listening on {}:{}",
-            config.network_interface,
-            config.network_port
+            config.host,
+            config.port
         );
 
         if !std::path::Path::new(&Textures::get_texture_path("flight", "ui/coconut.jpg")).exists() {
@@ -73,48 +155,219 @@ impl Client {
 
         Some(Client {
             node,
-            listener,
+            listener: Some(listener),
             server_id,
             local_addr,
             state_lock,
             config,
             events_tx,
+            encoder: BincodeEncoder::new(),
+            session: None,
+            token: None,
+            claimed: Vec::new(),
+            attempt: 0,
+            snapshots: VecDeque::with_capacity(SNAPSHOT_HISTORY),
+            stream: None,
+            streaming: false,
         })
     }
 
+    /// A handle for sending commands from other threads.
+    pub fn commands(&self) -> Commands {
+        Commands {
+            node: self.node.clone(),
+        }
+    }
+
     fn connect(node: &NodeHandler<Signal>, config: &Config) -> Option<(Endpoint, SocketAddr)> {
         let connection_result = node.network().connect(
             Transport::FramedTcp,
-            (config.network_interface, config.network_port),
+            (config.host.as_str(), config.port),
         );
 
         match connection_result {
             Err(_) => {
                 log::error!(
                     "Failed to listen on {}:{}",
-                    config.network_interface,
-                    config.network_port
+                    config.host,
+                    config.port
                 );
-                return None;
+                None
             }
 
-            Ok(res) => return Some(res),
+            Ok(res) => Some(res),
+        }
+    }
+
+    fn send(&mut self, message: FromClientMessage) {
+        let data = self.encoder.encode(message);
+        self.node.network().send(self.server_id, data);
+    }
+
+    /// Sends `Hello`, in the fixed handshake encoding.
+    fn send_hello(&mut self) {
+        let data = self.encoder.encode_handshake(FromClientMessage::Hello(Hello {
+            version: PROTOCOL_VERSION,
+            name: self.config.name.clone(),
+            role: self.config.role,
+            resume: self.token,
+        }));
+        self.node.network().send(self.server_id, data);
+    }
+
+    /// Completes the handshake, then claims the configured ship,
+    /// and the ships we controlled before losing the connection.
+    fn welcome(&mut self, welcome: Welcome) {
+        log::info!(
+            "Joined as {:?} {} (session {}, protocol version {})",
+            welcome.role,
+            self.config.name,
+            welcome.session,
+            welcome.version
+        );
+        self.session = Some(welcome.session);
+        self.token = Some(welcome.token);
+        self.attempt = 0;
+
+        // The server may have restarted, so the previous snapshots can't be trusted.
+        self.snapshots.clear();
+        self.state_lock.write().unwrap().clear();
+
+        send_or_log_err(&self.events_tx, NetThreadEvent::Connected);
+
+        if self.config.transport == TransportKind::Udp {
+            let stream = self.node.network().connect(
+                Transport::Udp,
+                (self.config.host.as_str(), self.config.port),
+            );
+
+            match stream {
+                Ok((stream, _)) => self.stream = Some(stream),
+                Err(_) => log::error!("Failed to open a UDP stream, snapshots will come over TCP"),
+            }
+        }
+
+        let mut ships = self.claimed.clone();
+        ships.extend(self.config.ship.filter(|ship| !ships.contains(ship)));
+        for ship in ships {
+            log::info!("Claiming ship {}", ship);
+            self.send(FromClientMessage::Claim(ship));
+        }
+    }
+
+    /// Forgets the lost connection, and schedules the next attempt to get it back.
+    /// The delay doubles with every failed attempt.
+    fn reconnect_later(&mut self) {
+        self.session = None;
+        self.snapshots.clear();
+        self.streaming = false;
+        if let Some(stream) = self.stream.take() {
+            self.node.network().remove(stream.resource_id());
+        }
+
+        let delay = RECONNECT_DELAY_MIN
+            .saturating_mul(1 << self.attempt.min(16))
+            .min(RECONNECT_DELAY_MAX);
+        self.attempt += 1;
+
+        log::info!(
+            "Reconnecting to {}:{} in {:.1}s (attempt {})",
+            self.config.host,
+            self.config.port,
+            delay.as_secs_f32(),
+            self.attempt
+        );
+        send_or_log_err(
+            &self.events_tx,
+            NetThreadEvent::Reconnecting {
+                attempt: self.attempt,
+                delay,
+            },
+        );
+        self.node.signals().send_with_timer(Signal::Reconnect, delay);
+    }
+
+    /// Asks the server for the UDP stream, and schedules a retry in case the request is lost.
+    fn request_stream(&mut self) {
+        let (Some(stream), Some(token)) = (self.stream, self.token) else {
+            return;
         };
+        if self.streaming {
+            return;
+        }
+
+        let data = self.encoder.encode(FromClientMessage::Stream { token });
+        self.node.network().send(stream, data);
+        self.node.signals().send_with_timer(Signal::Stream, STREAM_RETRY);
     }
 
-    pub fn run(self) {
+    /// Stores a received snapshot, hands it to the graphics thread and acks it.
+    /// Ticks act as sequence numbers: snapshots older than the latest one,
+    /// which UDP can deliver out of order, are dropped.
+    fn receive_snapshot(&mut self, tick: u64, state: State) {
+        if self.snapshots.back().is_some_and(|(latest, _)| *latest >= tick) {
+            log::debug!("Dropped stale snapshot {}", tick);
+            return;
+        }
+
+        let received = Instant::now();
+        self.state_lock.write().unwrap().push(state.clone(), received);
+
+        if self.snapshots.len() == SNAPSHOT_HISTORY {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back((tick, state));
+
+        self.send(FromClientMessage::Ack(tick));
+    }
+
+    /// Explains a message this build can't decode, and gives up if it comes from a server
+    /// that speaks another version of the protocol.
+    fn undecodable(&mut self, message_bin: &[u8]) {
+        if self.session.is_none() {
+            if let Some(reason) = peek_rejection(message_bin) {
+                log::error!("The server rejected this client: {}", reason);
+                self.node.signals().send_with_priority(Signal::Quit);
+                return;
+            }
+
+            if let Some(version) = peek_version(message_bin) {
+                log::error!(
+                    "Protocol version mismatch: the server speaks version {}, this client speaks version {}",
+                    version,
+                    PROTOCOL_VERSION
+                );
+                self.node.signals().send_with_priority(Signal::Quit);
+                return;
+            }
+        }
+
+        log::error!("Failed to decode message: the server sent an unknown message");
+    }
+
+    pub fn run(mut self) {
         let node_closer = self.node.clone();
 
         let ctrlc_handler_res = ctrlc::set_handler(move || {
             node_closer.signals().send(Signal::Quit);
         });
 
-        if let Err(_) = ctrlc_handler_res {
+        if ctrlc_handler_res.is_err() {
             log::error!("Error setting Ctrl-C handler");
         }
 
-        self.listener.for_each(move |message| match message {
+        let listener = self.listener.take().unwrap();
+        listener.for_each(move |message| match message {
             NodeEvent::Network(net_event) => match net_event {
+                NetEvent::Connected(endpoint, established) if Some(endpoint) == self.stream => {
+                    if established {
+                        self.request_stream();
+                    } else {
+                        log::error!("Failed to open a UDP stream, snapshots will come over TCP");
+                        self.stream = None;
+                    }
+                }
+                NetEvent::Connected(endpoint, _) if endpoint != self.server_id => {}
                 NetEvent::Connected(_, established) => {
                     if established {
                         log::info!("Connected to server at {}", self.server_id.addr());
@@ -123,30 +376,82 @@ impl Client {
                             self.local_addr.port()
                         );
 
-                        send_or_log_err(&self.events_tx, NetThreadEvent::Connected);
+                        self.send_hello();
                     } else {
-                        log::error!("Cannot connect to server at {}. Retrying...", self.server_id.addr());
-                        Client::connect(&self.node, &self.config);
+                        log::error!("Cannot connect to server at {}", self.server_id.addr());
+                        self.reconnect_later();
                     }
                 }
                 NetEvent::Accepted(_, _) => {}
-                NetEvent::Message(_, message_bin) => {
-                    let message: FromServerMessage = bincode::deserialize(&message_bin).unwrap();
+                NetEvent::Message(endpoint, message_bin) => {
+                    if Some(endpoint) == self.stream && !self.streaming {
+                        log::info!("Receiving snapshots over UDP");
+                        self.streaming = true;
+                    }
+
+                    // Until the handshake is done, the server answers in the handshake encoding.
+                    let message = if self.session.is_none() {
+                        BincodeEncoder::decode_handshake::<FromServerMessage>(message_bin)
+                    } else {
+                        BincodeEncoder::decode::<FromServerMessage>(message_bin)
+                    };
+                    let message = match message {
+                        Some(message) => message,
+                        None => return self.undecodable(message_bin),
+                    };
                     match message {
-                        FromServerMessage::Update(state) => {
-                            log::trace!("Received state update");
-                            log::trace!("Received state update");
-                            let mut state_guard = self.state_lock.write().unwrap();
-                            *state_guard = Some(state);
+                        FromServerMessage::Welcome(welcome) => self.welcome(welcome),
+                        FromServerMessage::Rejected(reason) => {
+                            log::error!("The server rejected this client: {}", reason);
+                            self.node.signals().send_with_priority(Signal::Quit);
+                        }
+                        FromServerMessage::Keyframe { tick, state } => {
+                            log::trace!("Received keyframe {}", tick);
+                            self.receive_snapshot(tick, state);
+                        }
+                        FromServerMessage::Delta {
+                            tick,
+                            baseline,
+                            delta,
+                        } => {
+                            log::trace!("Received delta {} from {}", tick, baseline);
+                            let state = self
+                                .snapshots
+                                .iter()
+                                .find(|(t, _)| *t == baseline)
+                                .and_then(|(_, baseline)| delta.apply(baseline));
+
+                            match state {
+                                Some(state) => self.receive_snapshot(tick, state),
+                                None => log::warn!(
+                                    "Dropped delta {}: baseline {} is unknown",
+                                    tick,
+                                    baseline
+                                ),
+                            }
+                        }
+                        FromServerMessage::Event(event) => {
+                            log::info!("Simulation event: {:?}", event);
+                        }
+                        FromServerMessage::Claimed(ship) => {
+                            log::info!("Now in control of ship {}", ship);
+                            if !self.claimed.contains(&ship) {
+                                self.claimed.push(ship);
+                            }
+                            send_or_log_err(&self.events_tx, NetThreadEvent::Claimed(ship));
+                        }
+                        FromServerMessage::Refused(reason) => {
+                            log::warn!("The server refused: {}", reason);
                         }
                     }
                 }
+                NetEvent::Disconnected(endpoint) if endpoint != self.server_id => {}
                 NetEvent::Disconnected(_) => {
-                    log::info!("Disconnected from server. Stopping...");
+                    log::warn!("Lost the connection to the server");
 
                     send_or_log_err(&self.events_tx, NetThreadEvent::Disconnected);
 
-                    self.node.signals().send_with_priority(Signal::Quit);
+                    self.reconnect_later();
                 }
             },
             NodeEvent::Signal(signal) => match signal {
@@ -157,6 +462,21 @@ impl Client {
                     log::trace!("Setting quit flag...");
                     send_or_log_err(&self.events_tx, NetThreadEvent::Quit);
                 }
+                Signal::Stream => self.request_stream(),
+                Signal::Reconnect => match Client::connect(&self.node, &self.config) {
+                    Some((server_id, local_addr)) => {
+                        self.server_id = server_id;
+                        self.local_addr = local_addr;
+                    }
+                    None => self.reconnect_later(),
+                },
+                Signal::Command(message) => {
+                    if self.session.is_some() {
+                        self.send(message);
+                    } else {
+                        log::debug!("Dropped {:?}: not connected", message);
+                    }
+                }
             },
         });
     }
@@ -1,4 +1,6 @@
 use clap::Parser;
+use common::data::settings::IntegratorKind;
+use common::messages::transport::TransportKind;
 
 /// The server for Newton. It is responsible for running the simulation and sending the data to the clients.
 #[derive(Parser, Debug)]
@@ -8,13 +10,28 @@ pub struct Arguments {
     #[clap(short, long)]
     pub system: String,
 
-    /// The network interface to bind to.
-    #[clap(short, long, default_value = "0.0.0.0")]
-    pub interface: String,
+    /// A RON file with network options. Options given on the command line take precedence.
+    #[clap(short, long)]
+    pub config: Option<String>,
+
+    /// The network interface to bind to, IPv4 or IPv6. Repeat to bind several.
+    /// Defaults to 0.0.0.0. On most systems :: accepts IPv4 clients too.
+    #[clap(short, long)]
+    pub interface: Vec<String>,
+
+    /// The network port to bind to. Defaults to 5000.
+    #[clap(short, long)]
+    pub port: Option<u16>,
+
+    /// How snapshots are sent to clients: tcp, or udp on the same port.
+    /// Commands always go over tcp. Defaults to tcp.
+    #[clap(long)]
+    pub transport: Option<TransportKind>,
 
-    /// The network port to bind to.
-    #[clap(short, long, default_value = "5000")]
-    pub port: u16,
+    /// The port to listen on for WebSocket clients, such as browser dashboards.
+    /// They speak JSON instead of bincode.
+    #[clap(long)]
+    pub ws_port: Option<u16>,
     
     /// The amount of softening applied to the simulation.
     #[clap(long, default_value = "0.1")]
@@ -28,12 +45,35 @@ pub struct Arguments {
     #[clap(short, long, default_value = "60")]
     pub target_fps: u32,
 
-    /// The minimum steps per update for the physics engine.
-    #[clap(long, default_value = "10")]
-    pub min_spu: u32,
+    /// The length of a physics step in simulated seconds, at 1x time warp.
+    #[clap(long, default_value = "0.01")]
+    pub timestep: f64,
+
+    /// The maximum number of steps the physics engine takes to catch up in one update.
+    #[clap(long, default_value = "1000")]
+    pub max_steps_per_update: u32,
+
+    /// The integrator used by the physics engine: leapfrog, verlet, rk4 or rkf45.
+    /// Overrides the integrator set in the system file.
+    #[clap(long)]
+    pub integrator: Option<IntegratorKind>,
 
-    /// The maximum steps per update for the physics engine.
+    /// The maximum local error per step, in meters, for the rkf45 integrator.
+    /// Overrides the tolerance set in the system file.
     #[clap(long)]
-    pub max_spu: Option<u32>,
+    pub tolerance: Option<f64>,
+
+    /// The Barnes-Hut opening angle. Lower is more accurate, higher is faster.
+    #[clap(long, default_value = "0.5")]
+    pub theta: f64,
+
+    /// The number of bodies from which gravity is computed with a Barnes-Hut tree
+    /// instead of direct summation.
+    #[clap(long, default_value = "512")]
+    pub barnes_hut_threshold: usize,
 
+    /// The number of updates between two full keyframes sent to each client.
+    /// Updates in between are sent as deltas.
+    #[clap(long, default_value = "120")]
+    pub keyframe_interval: u64,
 }
@@ -1,13 +1,15 @@
 use std::{
-    sync::{Arc, RwLock},
+    sync::{mpsc::channel, Arc, RwLock},
     thread,
 };
 
 use clap::Parser;
 use cli::arg_parser::Arguments;
 use env_logger::Env;
-use networking::server::{Config, Server};
+use networking::{config::Config, server::Server};
 use physics::{
+    forces::ForceModel,
+    integrators::integrator::new_integrator,
     physics_engine::PhysicsEngine,
     physics_runner::{run_physics, PhysicsConfig},
     state_loader::load_state,
@@ -26,25 +28,42 @@ fn main() {
 
     let arguments = Arguments::parse();
 
-    let mut physics = PhysicsEngine::new(arguments.softening);
+    let mut state = load_state(&arguments.system);
 
-    let state = load_state(&arguments.system);
+    if let Some(integrator) = arguments.integrator {
+        state.settings.integrator = integrator;
+    }
+    if let Some(tolerance) = arguments.tolerance {
+        state.settings.tolerance = tolerance;
+    }
 
-    let server_config = Config {
-        network_interface: "0.0.0.0",
-        network_port: 5000,
-    };
+    log::info!("Using the {} integrator", state.settings.integrator);
+
+    let integrator = new_integrator(state.settings.integrator, state.settings.tolerance);
+    let forces = ForceModel::new(
+        arguments.softening,
+        arguments.theta,
+        arguments.barnes_hut_threshold,
+        state.settings.ship_gravity,
+    );
+    let mut physics = PhysicsEngine::new(forces, integrator, state.settings.crash_speed);
+
+    let server_config = Config::new(&arguments);
 
     let inputs_rwlock = Arc::new(RwLock::new(state.inputs));
     let objects_rwlock = Arc::new(RwLock::new((state.ships, state.planets)));
+    let clock_rwlock = Arc::new(RwLock::new(state.clock));
 
     let inputs_rwlock_clone = inputs_rwlock.clone();
     let objects_rwlock_clone = objects_rwlock.clone();
+    let clock_rwlock_clone = clock_rwlock.clone();
+
+    let (events_tx, events_rx) = channel();
 
     let physics_config = PhysicsConfig {
         target_updates_per_s: arguments.target_fps,
-        min_spu: arguments.min_spu,
-        max_spu: arguments.max_spu,
+        timestep: arguments.timestep,
+        max_steps_per_update: arguments.max_steps_per_update,
     };
 
     thread::spawn(move || {
@@ -52,11 +71,21 @@ fn main() {
             &mut physics,
             objects_rwlock_clone,
             inputs_rwlock_clone,
+            clock_rwlock_clone,
+            events_tx,
             physics_config,
         );
     });
 
-    let server = Server::new(server_config, objects_rwlock, inputs_rwlock, &arguments);
+    let server = Server::new(
+        server_config,
+        objects_rwlock,
+        inputs_rwlock,
+        clock_rwlock,
+        events_rx,
+        state.settings,
+        &arguments,
+    );
     if server.is_none() {
         return;
     }
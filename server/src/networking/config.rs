@@ -0,0 +1,120 @@
+use std::{fs, process};
+
+use common::messages::transport::TransportKind;
+use ron::extensions::Extensions;
+use serde::Deserialize;
+
+use crate::cli::arg_parser::Arguments;
+
+/// Used when neither the command line nor the config file name an interface.
+pub const DEFAULT_INTERFACE: &str = "0.0.0.0";
+
+/// Used when neither the command line nor the config file name a port.
+pub const DEFAULT_PORT: u16 = 5000;
+
+/// What a listener speaks.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ListenerKind {
+    /// Framed TCP with bincode, as used by flight.
+    Tcp,
+
+    /// WebSocket with JSON, for browser clients.
+    Ws,
+}
+
+/// An address the server accepts clients on.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Listener {
+    /// An IPv4 or IPv6 address, such as `0.0.0.0` or `::`.
+    /// On most systems `::` accepts IPv4 clients too, so it can't share a port with `0.0.0.0`.
+    pub interface: String,
+    pub port: u16,
+    pub kind: ListenerKind,
+}
+
+/// Network options read from the config file given with `--config`.
+/// Every field is optional, and options given on the command line take precedence.
+/// Optional values can be written without `Some`.
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct ConfigFile {
+    /// Interfaces to accept TCP clients on, and WebSocket clients when `ws_port` is set.
+    pub interfaces: Vec<String>,
+    pub port: Option<u16>,
+    pub ws_port: Option<u16>,
+    pub transport: Option<TransportKind>,
+
+    /// Extra listeners, for combinations the options above can't express.
+    pub listeners: Vec<Listener>,
+}
+
+/// The network configuration of the server.
+#[derive(Debug)]
+pub struct Config {
+    pub listeners: Vec<Listener>,
+    pub transport: TransportKind,
+}
+
+impl Config {
+    /// Builds the configuration from the command line, and the config file if one is given.
+    pub fn new(args: &Arguments) -> Config {
+        let file = match &args.config {
+            Some(path) => load_config(path),
+            None => ConfigFile::default(),
+        };
+
+        let mut interfaces = if args.interface.is_empty() {
+            file.interfaces
+        } else {
+            args.interface.clone()
+        };
+        if interfaces.is_empty() {
+            interfaces.push(DEFAULT_INTERFACE.to_owned());
+        }
+
+        let port = args.port.or(file.port).unwrap_or(DEFAULT_PORT);
+        let ws_port = args.ws_port.or(file.ws_port);
+
+        let mut listeners = Vec::new();
+        for interface in interfaces {
+            if let Some(ws_port) = ws_port {
+                listeners.push(Listener {
+                    interface: interface.clone(),
+                    port: ws_port,
+                    kind: ListenerKind::Ws,
+                });
+            }
+            listeners.push(Listener {
+                interface,
+                port,
+                kind: ListenerKind::Tcp,
+            });
+        }
+        listeners.extend(file.listeners);
+
+        Config {
+            listeners,
+            transport: args.transport.or(file.transport).unwrap_or_default(),
+        }
+    }
+}
+
+pub fn load_config(path: &str) -> ConfigFile {
+    let config_file = match fs::read_to_string(path) {
+        Ok(val) => val,
+        Err(e) => {
+            log::error!("Failed to read config file: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let options = ron::Options::default().with_default_extension(Extensions::IMPLICIT_SOME);
+
+    match options.from_str(config_file.as_str()) {
+        Ok(val) => val,
+        Err(e) => {
+            log::error!("Failed to parse config file: {}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -1 +1,2 @@
+pub mod config;
 pub mod server;
\ No newline at end of file
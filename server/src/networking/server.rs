@@ -1,15 +1,22 @@
-use std::collections::HashSet;
-use std::ops::DerefMut;
-use std::sync::{Arc, RwLock};
-use std::time::Duration;
+use std::collections::hash_map::{Entry, RandomState};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::{mpsc::Receiver, Arc, RwLock};
+use std::time::{Duration, Instant};
 
-use common::data::inputs::Inputs;
+use common::data::clock::{Clock, WARP_LEVELS};
+use common::data::event::Event;
+use common::data::inputs::{Inputs, NavMode, ShipInputs};
 use common::data::object::Objects;
+use common::data::settings::Settings;
 use common::data::state::State;
-use common::messages::encoding::BincodeEncoder;
+use common::messages::delta::{StateDelta, SNAPSHOT_HISTORY};
+use common::messages::encoding::{BincodeEncoder, JsonEncoder};
 use common::messages::from_client::FromClientMessage;
 use common::messages::from_server::FromServerMessage;
-use message_io::network::{Endpoint, NetEvent};
+use common::messages::handshake::{peek_version, Hello, Role, Welcome, PROTOCOL_VERSION};
+use common::messages::transport::TransportKind;
+use message_io::network::{Endpoint, NetEvent, ResourceId};
 use message_io::node::{NodeEvent, NodeHandler};
 use message_io::{
     network::Transport,
@@ -18,32 +25,83 @@ use message_io::{
 
 use crate::cli::arg_parser::Arguments;
 
+use super::config::{Config, ListenerKind};
+
+/// How long the ships of a disconnected client are kept for it to resume its session.
+const RESUME_GRACE: Duration = Duration::from_secs(60);
+
 #[derive(Debug)]
 enum Signal {
     Update,
     Close,
 }
 
+/// Ships kept for a disconnected client, until it resumes its session or the grace period ends.
+struct Reservation {
+    ships: Vec<i32>,
+    until: Instant,
+}
+
+/// A client that completed the handshake.
+struct Session {
+    id: u64,
+
+    /// The secret the client gives back to resume this session after a disconnection.
+    token: u64,
+    name: String,
+    role: Role,
+
+    /// The latest tick the client acked, which deltas are computed from.
+    acked: Option<u64>,
+
+    /// The tick of the last keyframe sent to the client.
+    last_keyframe: Option<u64>,
+
+    /// The UDP endpoint snapshots are streamed to, if the client asked for one.
+    stream: Option<Endpoint>,
+}
+
 pub struct Server {
     encoder: BincodeEncoder,
+    json_encoder: JsonEncoder,
     objects_rwlock: Arc<RwLock<Objects>>,
-    inputs_rwlock: Arc<RwLock<Inputs>>,
+    inputs_rwlock: Arc<RwLock<ShipInputs>>,
+    clock_rwlock: Arc<RwLock<Clock>>,
+    events_rx: Receiver<Event>,
+    settings: Settings,
     listener: Option<NodeListener<Signal>>,
-    subscriptions: HashSet<Endpoint>,
+
+    /// The WebSocket listeners. Clients accepted by them speak JSON.
+    ws_listeners: HashSet<ResourceId>,
+    json_clients: HashSet<Endpoint>,
+
+    /// Clients that completed the handshake. Only these receive updates.
+    sessions: HashMap<Endpoint, Session>,
+    next_session: u64,
+
+    /// Reserved ships of disconnected clients, keyed by session token.
+    reservations: HashMap<u64, Reservation>,
+    random_state: RandomState,
+
+    /// Recently sent snapshots, oldest first, with their tick.
+    history: VecDeque<(u64, State)>,
+    tick: u64,
+    keyframe_interval: u64,
+
+    /// The client controlling each ship, keyed by ship id.
+    owners: HashMap<i32, Endpoint>,
     node: NodeHandler<Signal>,
     update_duration: Duration,
 }
 
-pub struct Config {
-    pub network_interface: &'static str,
-    pub network_port: u16,
-}
-
 impl Server {
     pub fn new(
         config: Config,
         objects_rwlock: Arc<RwLock<Objects>>,
-        input_rwlock: Arc<RwLock<Inputs>>,
+        input_rwlock: Arc<RwLock<ShipInputs>>,
+        clock_rwlock: Arc<RwLock<Clock>>,
+        events_rx: Receiver<Event>,
+        settings: Settings,
         args: &Arguments,
     ) -> Option<Server> {
         let (node, listener) = node::split();
@@ -53,48 +111,464 @@ impl Server {
             node_closer.signals().send_with_priority(Signal::Close);
         });
 
-        match ctrlc_handler_res {
-            Err(message) => log::error!("Failed to set ctrlc handler: {}", message),
-            _ => (),
-        }
-
-        if node
-            .network()
-            .listen(
-                Transport::FramedTcp,
-                (config.network_interface, config.network_port),
-            )
-            .is_err()
-        {
-            log::error!(
-                "Failed to listen on {}:{}",
-                config.network_interface,
-                config.network_port
-            );
-            return None;
+        if let Err(message) = ctrlc_handler_res {
+            log::error!("Failed to set ctrlc handler: {}", message);
         }
 
-        log::info!(
-            "Server is running on {}:{}",
-            config.network_interface,
-            config.network_port
-        );
+        let mut ws_listeners = HashSet::new();
+        for listener in &config.listeners {
+            let address = (listener.interface.as_str(), listener.port);
+
+            let transports: &[Transport] = match (listener.kind, config.transport) {
+                (ListenerKind::Tcp, TransportKind::Tcp) => &[Transport::FramedTcp],
+                (ListenerKind::Tcp, TransportKind::Udp) => &[Transport::FramedTcp, Transport::Udp],
+                (ListenerKind::Ws, _) => &[Transport::Ws],
+            };
+
+            for transport in transports {
+                match node.network().listen(*transport, address) {
+                    Ok((id, addr)) => {
+                        log::info!("Listening on {} ({:?})", addr, transport);
+                        if *transport == Transport::Ws {
+                            ws_listeners.insert(id);
+                        }
+                    }
+                    Err(e) => {
+                        log::error!(
+                            "Failed to listen on {}:{} ({:?}): {}",
+                            listener.interface,
+                            listener.port,
+                            transport,
+                            e
+                        );
+                        return None;
+                    }
+                }
+            }
+        }
+
+        log::info!("Server is running, sending snapshots over {}", config.transport);
 
         Some(Server {
             encoder: BincodeEncoder::new(),
+            json_encoder: JsonEncoder::new(),
             objects_rwlock,
             inputs_rwlock: input_rwlock,
+            clock_rwlock,
+            events_rx,
+            settings,
             listener: Some(listener),
-            subscriptions: HashSet::new(),
+            ws_listeners,
+            json_clients: HashSet::new(),
+            sessions: HashMap::new(),
+            next_session: 1,
+            reservations: HashMap::new(),
+            random_state: RandomState::new(),
+            history: VecDeque::with_capacity(SNAPSHOT_HISTORY),
+            tick: 0,
+            keyframe_interval: args.keyframe_interval.max(1),
+            owners: HashMap::new(),
             update_duration: Duration::from_secs_f32(1.0 / args.updates_per_second as f32),
             node,
         })
     }
 
     fn send_to_all_clients(&mut self, endpoints: Vec<Endpoint>, message: FromServerMessage) {
-        let data = self.encoder.encode(message);
+        let (json, binary): (Vec<Endpoint>, Vec<Endpoint>) = endpoints
+            .into_iter()
+            .partition(|endpoint| self.json_clients.contains(endpoint));
+
+        if !binary.is_empty() {
+            let data = self.encoder.encode(&message);
+            for endpoint in binary {
+                self.node.network().send(endpoint, data);
+            }
+        }
+
+        if !json.is_empty() {
+            let data = self.json_encoder.encode(&message);
+            for endpoint in json {
+                self.node.network().send(endpoint, data);
+            }
+        }
+    }
+
+    /// Sends the snapshot of the current tick to every client.
+    /// Clients get a delta from the last snapshot they acked, or a keyframe when they have none
+    /// recent enough, or when their last keyframe is too old.
+    /// JSON clients always get keyframes, so browser dashboards don't have to apply deltas.
+    fn send_snapshot(&mut self, state: State) {
+        self.tick += 1;
+        let tick = self.tick;
+
+        let endpoints: Vec<Endpoint> = self.sessions.keys().cloned().collect();
         for endpoint in endpoints {
-            self.node.network().send(endpoint, data);
+            let session = self.sessions.get_mut(&endpoint).unwrap();
+            let json = self.json_clients.contains(&endpoint);
+
+            let keyframe_due = json
+                || session
+                    .last_keyframe
+                    .is_none_or(|keyframe| tick - keyframe >= self.keyframe_interval);
+
+            let baseline = session
+                .acked
+                .filter(|_| !keyframe_due)
+                .and_then(|acked| self.history.iter().find(|(t, _)| *t == acked));
+
+            let delta = baseline.and_then(|(baseline_tick, baseline)| {
+                StateDelta::between(baseline, &state).map(|delta| (*baseline_tick, delta))
+            });
+
+            let message = match delta {
+                Some((baseline, delta)) => FromServerMessage::Delta {
+                    tick,
+                    baseline,
+                    delta,
+                },
+                None => {
+                    session.last_keyframe = Some(tick);
+                    FromServerMessage::Keyframe {
+                        tick,
+                        state: state.clone(),
+                    }
+                }
+            };
+
+            // Snapshots too large for a datagram fall back to TCP.
+            let stream = session.stream;
+            let data = if json {
+                self.json_encoder.encode(message)
+            } else {
+                self.encoder.encode(message)
+            };
+            match stream {
+                Some(stream) if data.len() <= Transport::Udp.max_message_size() => {
+                    self.node.network().send(stream, data);
+                }
+                _ => {
+                    self.node.network().send(endpoint, data);
+                }
+            }
+        }
+
+        if self.history.len() == SNAPSHOT_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back((tick, state));
+    }
+
+    fn send_to_client(&mut self, endpoint: Endpoint, message: FromServerMessage) {
+        let data = if self.json_clients.contains(&endpoint) {
+            self.json_encoder.encode(message)
+        } else {
+            self.encoder.encode(message)
+        };
+        self.node.network().send(endpoint, data);
+    }
+
+    /// Sends `Welcome` or `Rejected`, in the fixed handshake encoding for bincode clients.
+    fn send_handshake(&mut self, endpoint: Endpoint, message: FromServerMessage) {
+        let data = if self.json_clients.contains(&endpoint) {
+            self.json_encoder.encode(message)
+        } else {
+            self.encoder.encode_handshake(message)
+        };
+        self.node.network().send(endpoint, data);
+    }
+
+    /// Opens a session for a client, if it speaks our protocol version.
+    fn hello(&mut self, endpoint: Endpoint, hello: Hello) {
+        if hello.version != PROTOCOL_VERSION {
+            self.reject(endpoint, hello.version);
+            return;
+        }
+
+        if self.sessions.contains_key(&endpoint) {
+            log::warn!("{} said hello twice", endpoint);
+            return;
+        }
+
+        let id = self.next_session;
+        self.next_session += 1;
+
+        let now = Instant::now();
+        let resumed = hello.resume.and_then(|token| {
+            let ships = self.take_over(endpoint, token).or_else(|| {
+                self.reservations
+                    .remove(&token)
+                    .filter(|reservation| reservation.until > now)
+                    .map(|reservation| reservation.ships)
+            })?;
+            Some((token, ships))
+        });
+
+        let token = match &resumed {
+            Some((token, _)) => *token,
+            None => self.new_token(id),
+        };
+
+        match (&resumed, hello.resume) {
+            (Some(_), _) => log::info!(
+                "{} resumed as {:?} {} (session {})",
+                endpoint,
+                hello.role,
+                hello.name,
+                id
+            ),
+            (None, Some(_)) => log::info!(
+                "{} joined as {:?} {} (session {}), its previous session had expired",
+                endpoint,
+                hello.role,
+                hello.name,
+                id
+            ),
+            (None, None) => log::info!(
+                "{} joined as {:?} {} (session {})",
+                endpoint,
+                hello.role,
+                hello.name,
+                id
+            ),
+        }
+
+        self.send_handshake(
+            endpoint,
+            FromServerMessage::Welcome(Welcome {
+                version: PROTOCOL_VERSION,
+                session: id,
+                token,
+                role: hello.role,
+            }),
+        );
+
+        // Ships are only given back to clients that can still fly them.
+        if let Some((_, ships)) = resumed {
+            for ship in ships {
+                if hello.role == Role::Observer {
+                    log::info!("{} released ship {} by resuming as an observer", endpoint, ship);
+                    self.secure(ship);
+                } else if let Entry::Vacant(entry) = self.owners.entry(ship) {
+                    log::info!("{} controls ship {} again", endpoint, ship);
+                    entry.insert(endpoint);
+                    self.send_to_client(endpoint, FromServerMessage::Claimed(ship));
+                }
+            }
+        }
+
+        self.sessions.insert(
+            endpoint,
+            Session {
+                id,
+                token,
+                name: hello.name,
+                role: hello.role,
+                acked: None,
+                last_keyframe: None,
+                stream: None,
+            },
+        );
+    }
+
+    /// Closes the live session holding `token`, for a client resuming it from a new connection
+    /// before the server noticed the old one was lost. Returns the ships it controlled,
+    /// or `None` if no session holds `token`.
+    fn take_over(&mut self, endpoint: Endpoint, token: u64) -> Option<Vec<i32>> {
+        let previous = self
+            .sessions
+            .iter()
+            .find(|(_, session)| session.token == token)
+            .map(|(previous, _)| *previous)?;
+        let session = self.sessions.remove(&previous)?;
+
+        let ships = self
+            .owners
+            .iter()
+            .filter(|(_, owner)| **owner == previous)
+            .map(|(ship, _)| *ship)
+            .collect();
+        self.owners.retain(|_, owner| *owner != previous);
+        self.json_clients.remove(&previous);
+        self.node.network().remove(previous.resource_id());
+
+        log::info!(
+            "{} took over session {} from {}, which is now closed",
+            endpoint,
+            session.id,
+            previous
+        );
+        Some(ships)
+    }
+
+    /// Makes a session token that other clients can't guess.
+    fn new_token(&self, id: u64) -> u64 {
+        let mut hasher = self.random_state.build_hasher();
+        id.hash(&mut hasher);
+        Instant::now().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Keeps the ships of a disconnected client for a while, so it can resume its session.
+    /// They wait with their engine off.
+    fn reserve(&mut self, endpoint: Endpoint, session: &Session) {
+        let ships: Vec<i32> = self
+            .owners
+            .iter()
+            .filter(|(_, owner)| **owner == endpoint)
+            .map(|(ship, _)| *ship)
+            .collect();
+        self.owners.retain(|_, owner| *owner != endpoint);
+        for ship in &ships {
+            self.secure(*ship);
+        }
+
+        if !ships.is_empty() {
+            self.reservations.insert(
+                session.token,
+                Reservation {
+                    ships,
+                    until: Instant::now() + RESUME_GRACE,
+                },
+            );
+        }
+    }
+
+    /// Streams a session's snapshots to the UDP endpoint that asked for them with its token.
+    fn stream(&mut self, endpoint: Endpoint, token: u64) {
+        let session = self.sessions.values_mut().find(|session| session.token == token);
+
+        match session {
+            Some(session) => {
+                if session.stream != Some(endpoint) {
+                    log::info!("Streaming snapshots of session {} to {}", session.id, endpoint);
+                    session.stream = Some(endpoint);
+                }
+            }
+            None => log::warn!("{} asked for a stream with an unknown token", endpoint),
+        }
+    }
+
+    /// Tells a client built for another protocol version why it can't join, then drops it.
+    fn reject(&mut self, endpoint: Endpoint, version: u32) {
+        let reason = format!(
+            "Protocol version mismatch: the client speaks version {}, the server speaks version {}",
+            version, PROTOCOL_VERSION
+        );
+        log::warn!("Rejected {}: {}", endpoint, reason);
+        self.send_handshake(endpoint, FromServerMessage::Rejected(reason));
+        self.node.network().remove(endpoint.resource_id());
+    }
+
+    /// Gives a ship to a client, unless someone else already controls it,
+    /// or it is kept for a disconnected client. Admins may take ships from other clients.
+    fn claim(&mut self, endpoint: Endpoint, role: Role, ship: i32) {
+        let now = Instant::now();
+        let reserved = self
+            .reservations
+            .values()
+            .any(|r| r.until > now && r.ships.contains(&ship));
+
+        let ship_exists = self
+            .objects_rwlock
+            .read()
+            .unwrap()
+            .0
+            .iter()
+            .any(|s| s.object.id == ship);
+
+        let refusal = match self.owners.get(&ship) {
+            _ if role == Role::Observer => Some("Observers cannot fly ships".to_owned()),
+            _ if !ship_exists => Some(format!("There is no ship {}", ship)),
+            Some(owner) if *owner != endpoint && role != Role::Admin => {
+                Some(format!("Ship {} is controlled by another client", ship))
+            }
+            None if reserved && role != Role::Admin => Some(format!(
+                "Ship {} is kept for a pilot who lost their connection",
+                ship
+            )),
+            _ => None,
+        };
+
+        match refusal {
+            Some(reason) => {
+                log::info!("Refused claim of ship {} by {}: {}", ship, endpoint, reason);
+                self.send_to_client(endpoint, FromServerMessage::Refused(reason));
+            }
+            None => {
+                log::info!("{} now controls ship {}", endpoint, ship);
+                self.owners.insert(ship, endpoint);
+                self.send_to_client(endpoint, FromServerMessage::Claimed(ship));
+            }
+        }
+    }
+
+    /// Applies a command to a ship's inputs, if the client controls that ship.
+    fn command(&mut self, endpoint: Endpoint, ship: i32, apply: impl FnOnce(&mut Inputs)) {
+        if self.owners.get(&ship) != Some(&endpoint) {
+            log::warn!("{} sent a command for ship {}, which it does not control", endpoint, ship);
+            let reason = format!("You do not control ship {}", ship);
+            self.send_to_client(endpoint, FromServerMessage::Refused(reason));
+            return;
+        }
+
+        let mut inputs = self.inputs_rwlock.write().unwrap();
+        apply(inputs.entry(ship).or_default());
+    }
+
+    /// Cuts the engine of a ship nobody flies anymore, and holds its heading.
+    fn secure(&mut self, ship: i32) {
+        let mut inputs = self.inputs_rwlock.write().unwrap();
+        let inputs = inputs.entry(ship).or_default();
+        inputs.throttle = 0.;
+        inputs.navmode = NavMode::HOLD;
+    }
+
+    /// Handles a message. Apart from `Hello` and `Stream`, which come before a session
+    /// or from its UDP endpoint, messages are only accepted from clients with a session.
+    fn handle_message(&mut self, endpoint: Endpoint, message: FromClientMessage) {
+        let role = self.sessions.get(&endpoint).map(|session| session.role);
+
+        match (message, role) {
+            (FromClientMessage::Hello(hello), _) => self.hello(endpoint, hello),
+            (FromClientMessage::Stream { token }, _) => self.stream(endpoint, token),
+            (_, None) => log::warn!("{} sent a message before saying hello", endpoint),
+            (FromClientMessage::Claim(ship), Some(role)) => self.claim(endpoint, role, ship),
+            (FromClientMessage::Release(ship), Some(_)) => {
+                if self.owners.get(&ship) == Some(&endpoint) {
+                    log::info!("{} released ship {}", endpoint, ship);
+                    self.owners.remove(&ship);
+                    self.secure(ship);
+                }
+            }
+            (FromClientMessage::NavMode { ship, navmode }, Some(_)) => {
+                self.command(endpoint, ship, |inputs| inputs.navmode = navmode);
+            }
+            (FromClientMessage::Throttle { ship, throttle }, Some(_)) => {
+                let throttle = throttle.clamp(0., 1.);
+                self.command(endpoint, ship, |inputs| inputs.throttle = throttle);
+            }
+            (FromClientMessage::Reference { ship, reference }, Some(_)) => {
+                self.command(endpoint, ship, |inputs| inputs.reference = reference);
+            }
+            (FromClientMessage::Target { ship, target }, Some(_)) => {
+                self.command(endpoint, ship, |inputs| inputs.target = target);
+            }
+            (FromClientMessage::Ack(tick), Some(_)) => {
+                if let Some(session) = self.sessions.get_mut(&endpoint) {
+                    if tick <= self.tick && session.acked.is_none_or(|acked| tick > acked) {
+                        session.acked = Some(tick);
+                    }
+                }
+            }
+            (FromClientMessage::Warp(_), Some(Role::Observer)) => {
+                let reason = "Observers cannot change the time warp".to_owned();
+                self.send_to_client(endpoint, FromServerMessage::Refused(reason));
+            }
+            (FromClientMessage::Warp(warp), Some(_)) => {
+                let warp = warp.min(WARP_LEVELS.len() - 1);
+                log::info!("{} requested {}x time warp", endpoint, WARP_LEVELS[warp]);
+                self.clock_rwlock.write().unwrap().requested_warp = warp;
+            }
         }
     }
 
@@ -108,57 +582,95 @@ impl Server {
                     self.node.stop();
                 }
                 Signal::Update => {
+                    let now = Instant::now();
+                    self.reservations.retain(|_, reservation| reservation.until > now);
+
                     let objects_guard = self.objects_rwlock.read().unwrap();
                     let inputs_guard = self.inputs_rwlock.read().unwrap();
                     let inputs = inputs_guard.clone();
                     let (ships, planets) = objects_guard.clone();
                     drop(objects_guard);
                     drop(inputs_guard);
+                    let clock = self.clock_rwlock.read().unwrap().clone();
 
                     let sent_state = State {
                         inputs,
                         planets,
                         ships,
+                        settings: self.settings.clone(),
+                        clock,
                     };
 
-                    let message = FromServerMessage::Update(sent_state);
-                    let subscriptions = self.subscriptions.iter().cloned().collect();
-                    self.send_to_all_clients(subscriptions, message);
+                    self.send_snapshot(sent_state);
+
+                    let subscriptions: Vec<Endpoint> = self.sessions.keys().cloned().collect();
+
+                    while let Ok(event) = self.events_rx.try_recv() {
+                        let message = FromServerMessage::Event(event);
+                        self.send_to_all_clients(subscriptions.clone(), message);
+                    }
                     self.node
                         .signals()
                         .send_with_timer(Signal::Update, self.update_duration);
                 }
             },
             NodeEvent::Network(network) => match network {
-                NetEvent::Accepted(endpoint, _) => {
-                    log::info!("Client connected: {}", endpoint);
-                    self.subscriptions.insert(endpoint);
+                NetEvent::Accepted(endpoint, listener_id) => {
+                    if self.ws_listeners.contains(&listener_id) {
+                        log::info!("WebSocket client connected: {}", endpoint);
+                        self.json_clients.insert(endpoint);
+                    } else {
+                        log::info!("Client connected: {}", endpoint);
+                    }
                 }
                 NetEvent::Connected(_, _) => (),
                 NetEvent::Disconnected(endpoint) => {
-                    log::info!("Client disconnected: {}", endpoint);
-                    self.subscriptions.remove(&endpoint);
+                    match self.sessions.remove(&endpoint) {
+                        Some(session) => {
+                            log::info!(
+                                "Client disconnected: {} ({}, session {})",
+                                endpoint,
+                                session.name,
+                                session.id
+                            );
+                            self.reserve(endpoint, &session);
+                        }
+                        None => log::info!("Client disconnected: {}", endpoint),
+                    }
+                    self.owners.retain(|_, owner| *owner != endpoint);
+                    self.json_clients.remove(&endpoint);
                 }
                 NetEvent::Message(endpoint, data) => {
-                    match BincodeEncoder::decode::<FromClientMessage>(data) {
-                        Some(message) => {
-                            let mut guard = self.inputs_rwlock.write().unwrap();
-                            let mut inputs = guard.deref_mut();
-                            match message {
-                                FromClientMessage::NavMode(navmode) => {
-                                    inputs.navmode = navmode;
-                                }
-                                FromClientMessage::Throttle(throttle) => {
-                                    inputs.throttle = throttle;
+                    let json = self.json_clients.contains(&endpoint);
+                    let message = if json {
+                        JsonEncoder::decode::<FromClientMessage>(data)
+                    } else if self.sessions.contains_key(&endpoint) {
+                        BincodeEncoder::decode::<FromClientMessage>(data)
+                    } else {
+                        // Before the handshake, a Hello comes in the handshake encoding,
+                        // and UDP stream requests in the usual one.
+                        BincodeEncoder::decode_handshake::<FromClientMessage>(data)
+                            .filter(|message| matches!(message, FromClientMessage::Hello(_)))
+                            .or_else(|| BincodeEncoder::decode::<FromClientMessage>(data))
+                    };
+
+                    match message {
+                        Some(message) => self.handle_message(endpoint, message),
+                        None => match peek_version(data) {
+                            Some(version) if !json && version != PROTOCOL_VERSION => {
+                                self.reject(endpoint, version);
+                            }
+                            _ => {
+                                log::error!(
+                                    "Failed to decode message: {} sent an unknown messsage",
+                                    endpoint
+                                );
+                                if json {
+                                    let reason = "Unknown message".to_owned();
+                                    self.send_to_client(endpoint, FromServerMessage::Refused(reason));
                                 }
                             }
-                        }
-                        None => {
-                            log::error!(
-                                "Failed to decode message: {} sent an unknown messsage",
-                                endpoint
-                            );
-                        }
+                        },
                     }
                 }
             },
@@ -0,0 +1,110 @@
+use std::f64::consts::PI;
+
+use common::{
+    data::{
+        inputs::{inputs_for, Inputs, NavMode, ShipInputs},
+        object::Objects,
+    },
+    orbit::wrap_angle,
+};
+
+use super::integrators::integrator::Phase;
+
+/// Finds the row of a body in the phase arrays, from its id.
+fn find_row(objects: &Objects, id: i32) -> Option<usize> {
+    let (ships, planets) = objects;
+    planets.iter().position(|p| p.object.id == id).or_else(|| {
+        ships
+            .iter()
+            .position(|s| s.object.id == id)
+            .map(|i| planets.len() + i)
+    })
+}
+
+/// The row of the planet with the strongest pull on the body at `row`.
+fn dominant_row(objects: &Objects, phase: &Phase, row: usize) -> Option<usize> {
+    let (_, planets) = objects;
+
+    planets
+        .iter()
+        .enumerate()
+        .map(|(planet_row, planet)| {
+            let offset = &phase.positions.row(planet_row) - &phase.positions.row(row);
+            (planet_row, planet.object.mass / offset.dot(&offset))
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(planet_row, _)| planet_row)
+}
+
+/// The heading a nav mode points to, or `None` if it doesn't point anywhere.
+fn desired_heading(objects: &Objects, phase: &Phase, inputs: &Inputs, row: usize) -> Option<f64> {
+    let angle_of = |x: f64, y: f64| y.atan2(x);
+
+    let reference_row = match inputs.reference {
+        Some(id) => find_row(objects, id),
+        None => dominant_row(objects, phase, row),
+    };
+    let target_row = inputs.target.and_then(|id| find_row(objects, id));
+
+    let radial = reference_row.map(|r| &phase.positions.row(row) - &phase.positions.row(r));
+    let velocity = reference_row.map(|r| &phase.velocities.row(row) - &phase.velocities.row(r));
+    let to_target = target_row.map(|t| &phase.positions.row(t) - &phase.positions.row(row));
+
+    match inputs.navmode {
+        NavMode::MAN | NavMode::HOLD => None,
+        NavMode::CCWPROG => radial.map(|r| angle_of(r[0], r[1]) + PI / 2.),
+        NavMode::CWPROG => radial.map(|r| angle_of(r[0], r[1]) - PI / 2.),
+        NavMode::PROG => velocity.map(|v| angle_of(v[0], v[1])),
+        NavMode::RETRO => velocity.map(|v| angle_of(v[0], v[1]) + PI),
+        NavMode::RADOUT => radial.map(|r| angle_of(r[0], r[1])),
+        NavMode::RADIN => radial.map(|r| angle_of(r[0], r[1]) + PI),
+        NavMode::TTARG => to_target.map(|t| angle_of(t[0], t[1])),
+        NavMode::ATARG => to_target.map(|t| angle_of(t[0], t[1]) + PI),
+    }
+}
+
+/// The spin to aim for to reach `heading` as fast as possible, while still
+/// being able to brake in time with `max_angular_acceleration`.
+fn desired_spin(current: f64, heading: f64, max_angular_acceleration: f64, dt: f64) -> f64 {
+    let error = wrap_angle(heading - current);
+    let braking_spin = (2. * max_angular_acceleration * error.abs()).sqrt();
+
+    // Never aim to cover more than the remaining error in a single step.
+    error.signum() * braking_spin.min(error.abs() / dt)
+}
+
+/// Drives the spin of every ship toward what its nav mode asks for.
+/// The spin changes by at most the ship's maximum angular acceleration.
+pub fn steer(objects: &mut Objects, phase: &Phase, inputs: &ShipInputs, dt: f64) {
+    let planet_count = objects.1.len();
+
+    let target_spins: Vec<Option<f64>> = (0..objects.0.len())
+        .map(|ship_index| {
+            let ship = &objects.0[ship_index];
+            let max_angular_acceleration = ship.max_angular_acceleration();
+            let inputs = &inputs_for(inputs, ship.object.id);
+
+            match inputs.navmode {
+                NavMode::MAN => None,
+                NavMode::HOLD => Some(0.),
+                _ => desired_heading(objects, phase, inputs, planet_count + ship_index).map(
+                    |heading| {
+                        desired_spin(ship.object.heading, heading, max_angular_acceleration, dt)
+                    },
+                ),
+            }
+        })
+        .collect();
+
+    for (ship, target_spin) in objects.0.iter_mut().zip(target_spins) {
+        // Landed ships turn with their planet.
+        if ship.landed.is_some() {
+            continue;
+        }
+
+        if let Some(target_spin) = target_spin {
+            let max_change = ship.max_angular_acceleration() * dt;
+            ship.object.spin += (target_spin - ship.object.spin).clamp(-max_change, max_change);
+        }
+    }
+}
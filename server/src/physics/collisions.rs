@@ -0,0 +1,318 @@
+use common::data::{
+    event::Event,
+    object::{Landing, Objects, Planet, Ship},
+};
+
+use super::integrators::integrator::Phase;
+
+/// Restitution of ship to ship collisions.
+/// 0 is perfectly inelastic, 1 is perfectly elastic.
+const RESTITUTION: f64 = 0.5;
+
+fn get_row(array: &ndarray::Array2<f64>, row: usize) -> [f64; 2] {
+    [array[[row, 0]], array[[row, 1]]]
+}
+
+fn set_row(array: &mut ndarray::Array2<f64>, row: usize, value: [f64; 2]) {
+    array[[row, 0]] = value[0];
+    array[[row, 1]] = value[1];
+}
+
+fn dot(a: [f64; 2], b: [f64; 2]) -> f64 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+/// Position and velocity of a point fixed to a planet's rotating surface,
+/// `distance` away from its center, in the direction `direction` (unit vector).
+fn surface_point(
+    phase: &Phase,
+    planet_row: usize,
+    planet: &Planet,
+    direction: [f64; 2],
+    distance: f64,
+) -> ([f64; 2], [f64; 2]) {
+    let center = get_row(&phase.positions, planet_row);
+    let center_velocity = get_row(&phase.velocities, planet_row);
+    let offset = [direction[0] * distance, direction[1] * distance];
+    let spin = planet.object.spin;
+
+    (
+        [center[0] + offset[0], center[1] + offset[1]],
+        [
+            center_velocity[0] - spin * offset[1],
+            center_velocity[1] + spin * offset[0],
+        ],
+    )
+}
+
+/// Wrecks a crashed ship: its fuel is lost, so its engine can't fire again.
+fn wreck(ship: &mut Ship) {
+    ship.set_fuel_mass(0.);
+    ship.object.mass = ship.mass();
+}
+
+/// Keeps landed ships on their planet's surface, lifts them off when they thrust
+/// hard enough, and lands ships that touch a surface.
+fn resolve_planet_contacts(
+    objects: &mut Objects,
+    phase: &mut Phase,
+    crash_speed: f64,
+    events: &mut Vec<Event>,
+) {
+    let (ships, planets) = objects;
+    let planet_count = planets.len();
+
+    for (ship_index, ship) in ships.iter_mut().enumerate() {
+        let row = planet_count + ship_index;
+        let ship_radius = ship.radius();
+
+        if let Some(landing) = &ship.landed {
+            let planet_row = planets.iter().position(|p| p.object.id == landing.planet);
+            let Some(planet_row) = planet_row else {
+                log::warn!(
+                    "Ship {} is landed on unknown planet {}",
+                    ship.object.id,
+                    landing.planet
+                );
+                ship.landed = None;
+                continue;
+            };
+            let planet = &planets[planet_row];
+
+            let angle = planet.object.heading + landing.angle;
+            let normal = [angle.cos(), angle.sin()];
+            let distance = planet.radius + ship_radius;
+
+            // The surface can push the ship, but not pull it. The ship lifts off
+            // once its acceleration away from the surface beats what is needed
+            // to keep turning with the planet.
+            let ship_acceleration = get_row(&phase.accelerations, row);
+            let planet_acceleration = get_row(&phase.accelerations, planet_row);
+            let relative_acceleration = [
+                ship_acceleration[0] - planet_acceleration[0],
+                ship_acceleration[1] - planet_acceleration[1],
+            ];
+            let centripetal = planet.object.spin.powi(2) * distance;
+
+            if dot(relative_acceleration, normal) > -centripetal {
+                log::info!(
+                    "Ship {} lifted off planet {}",
+                    ship.object.id,
+                    planet.object.id
+                );
+                events.push(Event::LiftedOff {
+                    ship: ship.object.id,
+                    planet: planet.object.id,
+                });
+                ship.landed = None;
+                continue;
+            }
+
+            let (position, velocity) = surface_point(phase, planet_row, planet, normal, distance);
+            set_row(&mut phase.positions, row, position);
+            set_row(&mut phase.velocities, row, velocity);
+            continue;
+        }
+
+        for (planet_row, planet) in planets.iter().enumerate() {
+            let ship_position = get_row(&phase.positions, row);
+            let planet_position = get_row(&phase.positions, planet_row);
+            let offset = [
+                ship_position[0] - planet_position[0],
+                ship_position[1] - planet_position[1],
+            ];
+            let offset_length = dot(offset, offset).sqrt();
+            let distance = planet.radius + ship_radius;
+
+            if offset_length >= distance {
+                continue;
+            }
+
+            let normal = if offset_length > 0. {
+                [offset[0] / offset_length, offset[1] / offset_length]
+            } else {
+                [1., 0.]
+            };
+
+            let (surface_position, surface_velocity) =
+                surface_point(phase, planet_row, planet, normal, distance);
+            let ship_velocity = get_row(&phase.velocities, row);
+            let relative_velocity = [
+                ship_velocity[0] - surface_velocity[0],
+                ship_velocity[1] - surface_velocity[1],
+            ];
+
+            set_row(&mut phase.positions, row, surface_position);
+
+            // A ship already moving away from the surface, for example right
+            // after lifting off, is only pushed back out.
+            if dot(relative_velocity, normal) >= 0. {
+                continue;
+            }
+
+            let impact_speed = dot(relative_velocity, relative_velocity).sqrt();
+            if impact_speed > crash_speed {
+                log::info!(
+                    "Ship {} crashed into planet {} at {:.1} m/s",
+                    ship.object.id,
+                    planet.object.id,
+                    impact_speed
+                );
+                events.push(Event::Crashed {
+                    ship: ship.object.id,
+                    other: planet.object.id,
+                    speed: impact_speed,
+                });
+
+                // The wreck stays where it hit, and can't lift off without fuel.
+                wreck(ship);
+            } else {
+                log::info!(
+                    "Ship {} landed on planet {}",
+                    ship.object.id,
+                    planet.object.id
+                );
+                events.push(Event::Landed {
+                    ship: ship.object.id,
+                    planet: planet.object.id,
+                });
+            }
+
+            set_row(&mut phase.velocities, row, surface_velocity);
+            ship.landed = Some(Landing {
+                planet: planet.object.id,
+                angle: normal[1].atan2(normal[0]) - planet.object.heading,
+            });
+            break;
+        }
+    }
+}
+
+/// Separates overlapping ships, and bounces them off each other.
+/// Landed ships are left to the planet contacts.
+fn resolve_ship_contacts(
+    objects: &mut Objects,
+    phase: &mut Phase,
+    crash_speed: f64,
+    events: &mut Vec<Event>,
+) {
+    let (ships, planets) = objects;
+    let planet_count = planets.len();
+    let mut wrecked = Vec::new();
+
+    for (i, ship_i) in ships.iter().enumerate() {
+        for (j, ship_j) in ships.iter().enumerate().skip(i + 1) {
+            if ship_i.landed.is_some() || ship_j.landed.is_some() {
+                continue;
+            }
+
+            let (row_i, row_j) = (planet_count + i, planet_count + j);
+            let position_i = get_row(&phase.positions, row_i);
+            let position_j = get_row(&phase.positions, row_j);
+            let offset = [position_j[0] - position_i[0], position_j[1] - position_i[1]];
+            let distance = dot(offset, offset).sqrt();
+            let contact_distance = ship_i.radius() + ship_j.radius();
+
+            if distance >= contact_distance {
+                continue;
+            }
+
+            let normal = if distance > 0. {
+                [offset[0] / distance, offset[1] / distance]
+            } else {
+                [1., 0.]
+            };
+
+            let (mass_i, mass_j) = (ship_i.object.mass, ship_j.object.mass);
+            let total_mass = mass_i + mass_j;
+            let (share_i, share_j) = if total_mass > 0. {
+                (mass_j / total_mass, mass_i / total_mass)
+            } else {
+                (0.5, 0.5)
+            };
+
+            // Push the ships apart, the lighter one moving the most.
+            let overlap = contact_distance - distance;
+            set_row(
+                &mut phase.positions,
+                row_i,
+                [
+                    position_i[0] - normal[0] * overlap * share_i,
+                    position_i[1] - normal[1] * overlap * share_i,
+                ],
+            );
+            set_row(
+                &mut phase.positions,
+                row_j,
+                [
+                    position_j[0] + normal[0] * overlap * share_j,
+                    position_j[1] + normal[1] * overlap * share_j,
+                ],
+            );
+
+            let velocity_i = get_row(&phase.velocities, row_i);
+            let velocity_j = get_row(&phase.velocities, row_j);
+            let relative_velocity = [velocity_j[0] - velocity_i[0], velocity_j[1] - velocity_i[1]];
+            let closing_speed = dot(relative_velocity, normal);
+
+            if closing_speed >= 0. {
+                continue;
+            }
+
+            let impulse = (1. + RESTITUTION) * closing_speed;
+            set_row(
+                &mut phase.velocities,
+                row_i,
+                [
+                    velocity_i[0] + normal[0] * impulse * share_i,
+                    velocity_i[1] + normal[1] * impulse * share_i,
+                ],
+            );
+            set_row(
+                &mut phase.velocities,
+                row_j,
+                [
+                    velocity_j[0] - normal[0] * impulse * share_j,
+                    velocity_j[1] - normal[1] * impulse * share_j,
+                ],
+            );
+
+            let impact_speed = dot(relative_velocity, relative_velocity).sqrt();
+            if impact_speed > crash_speed {
+                log::info!(
+                    "Ships {} and {} crashed at {:.1} m/s",
+                    ship_i.object.id,
+                    ship_j.object.id,
+                    impact_speed
+                );
+                events.push(Event::Crashed {
+                    ship: ship_i.object.id,
+                    other: ship_j.object.id,
+                    speed: impact_speed,
+                });
+                events.push(Event::Crashed {
+                    ship: ship_j.object.id,
+                    other: ship_i.object.id,
+                    speed: impact_speed,
+                });
+                wrecked.extend([i, j]);
+            }
+        }
+    }
+
+    for index in wrecked {
+        wreck(&mut ships[index]);
+    }
+}
+
+/// Detects and resolves contacts between ships and planet surfaces, and between ships.
+/// Runs after every integration step, and pushes what happened to `events`.
+pub fn resolve_collisions(
+    objects: &mut Objects,
+    phase: &mut Phase,
+    crash_speed: f64,
+    events: &mut Vec<Event>,
+) {
+    resolve_planet_contacts(objects, phase, crash_speed, events);
+    resolve_ship_contacts(objects, phase, crash_speed, events);
+}
@@ -0,0 +1,158 @@
+use ndarray::{s, Array2};
+
+use common::{
+    data::{
+        inputs::{inputs_for, ShipInputs},
+        object::{Objects, ShipType},
+    },
+    orbit::gravity::G,
+};
+
+use super::gravity::{barnes_hut::BarnesHutSolver, direct::DirectSolver, solver::GravitySolver};
+
+/// Computes the accelerations acting on every body.
+/// Bodies are ordered planets first, then ships, in every array.
+pub struct ForceModel {
+    pub softening: f64,
+
+    /// From this many bodies on, gravity is computed with the Barnes-Hut tree
+    /// instead of direct summation.
+    pub barnes_hut_threshold: usize,
+
+    /// Whether ships are sources of gravity, like planets.
+    pub ship_gravity: bool,
+
+    direct: DirectSolver,
+    barnes_hut: BarnesHutSolver,
+}
+
+impl ForceModel {
+    pub fn new(
+        softening: f64,
+        theta: f64,
+        barnes_hut_threshold: usize,
+        ship_gravity: bool,
+    ) -> ForceModel {
+        ForceModel {
+            softening,
+            barnes_hut_threshold,
+            ship_gravity,
+            direct: DirectSolver,
+            barnes_hut: BarnesHutSolver::new(theta),
+        }
+    }
+
+    /// Picks the cheapest gravity solver for the number of bodies.
+    fn solver(&self, body_count: usize) -> &dyn GravitySolver {
+        if body_count >= self.barnes_hut_threshold {
+            &self.barnes_hut
+        } else {
+            &self.direct
+        }
+    }
+
+    fn get_thrust_accelerations(&self, objects: &Objects, inputs: &ShipInputs) -> Array2<f64> {
+        let (ships, _) = objects;
+        let mut ship_accelerations = Array2::zeros((ships.len(), 2));
+
+        for (ship_index, ship) in ships.iter().enumerate() {
+            let throttle = inputs_for(inputs, ship.object.id).throttle;
+
+            match &ship.ship_type {
+                ShipType::HAB(data) => {
+                    // The engine cuts out when the tank is empty.
+                    if data.fuel_mass <= 0. || data.mass() <= 0. {
+                        continue;
+                    }
+
+                    let mut acceleration = ship_accelerations.slice_mut(s![ship_index, ..]);
+                    let heading_vector =
+                        ndarray::arr1(&[ship.object.heading.cos(), ship.object.heading.sin()]);
+                    let acc_from_engine =
+                        heading_vector * data.thrust as f64 * throttle as f64 / data.mass();
+                    acceleration += &acc_from_engine;
+                }
+            }
+        }
+
+        ship_accelerations
+    }
+
+    /// Drag from every atmosphere a ship is flying through.
+    /// The air turns with its planet, so drag depends on the velocity relative to the rotating air.
+    fn get_drag_accelerations(
+        &self,
+        objects: &Objects,
+        positions: &Array2<f64>,
+        velocities: &Array2<f64>,
+    ) -> Array2<f64> {
+        let (ships, planets) = objects;
+        let planet_count = planets.len();
+        let mut drag_accelerations = Array2::zeros((ships.len(), 2));
+
+        for (ship_index, ship) in ships.iter().enumerate() {
+            if ship.object.mass <= 0. {
+                continue;
+            }
+
+            let row = planet_count + ship_index;
+
+            for (planet_index, planet) in planets.iter().enumerate() {
+                let Some(atmosphere) = &planet.atmosphere else {
+                    continue;
+                };
+
+                let offset = &positions.row(row) - &positions.row(planet_index);
+                let altitude = offset.dot(&offset).sqrt() - planet.radius;
+                let density = atmosphere.density(altitude);
+                if density <= 0. {
+                    continue;
+                }
+
+                let spin = planet.object.spin;
+                let air_velocity = &velocities.row(planet_index)
+                    + &ndarray::arr1(&[-spin * offset[1], spin * offset[0]]);
+                let relative_velocity = &velocities.row(row) - &air_velocity;
+                let speed = relative_velocity.dot(&relative_velocity).sqrt();
+
+                // F = 1/2 rho v² Cd A, against the relative velocity.
+                let drag = -0.5 * density * speed * ship.drag_area() / ship.object.mass;
+                let mut acceleration = drag_accelerations.slice_mut(s![ship_index, ..]);
+                acceleration.scaled_add(drag, &relative_velocity);
+            }
+        }
+
+        drag_accelerations
+    }
+
+    /// Gets the acceleration of every body, from their current positions and velocities.
+    pub fn get_accelerations(
+        &self,
+        objects: &Objects,
+        inputs: &ShipInputs,
+        positions: &Array2<f64>,
+        velocities: &Array2<f64>,
+    ) -> Array2<f64> {
+        let (ships, planets) = objects;
+        let planet_count = planets.len();
+
+        // Planets are always sources of gravity. Ships are only sources when
+        // ship gravity is on, and are test particles otherwise.
+        let mut masses: Vec<f64> = planets.iter().map(|p| p.object.mass).collect();
+        if self.ship_gravity {
+            masses.extend(ships.iter().map(|s| s.object.mass));
+        }
+
+        let mut accelerations =
+            self.solver(positions.nrows())
+                .field(positions.view(), &masses, self.softening);
+
+        accelerations.map_inplace(|a| *a *= G);
+
+        let mut ship_accelerations = accelerations.slice_mut(s![planet_count.., ..]);
+        ship_accelerations += &self.get_thrust_accelerations(objects, inputs);
+        ship_accelerations += &self.get_drag_accelerations(objects, positions, velocities);
+
+        accelerations
+    }
+}
@@ -0,0 +1,243 @@
+use ndarray::{Array2, ArrayView2};
+
+use super::solver::GravitySolver;
+
+/// Past this depth, bodies are stored together in the same leaf instead of
+/// splitting the node further. Prevents endless splitting when bodies overlap.
+const MAX_DEPTH: u32 = 48;
+
+/// A square region of space in the quadtree.
+struct Node {
+    center: [f64; 2],
+    half_size: f64,
+
+    /// Total mass of the sources in this node.
+    mass: f64,
+
+    /// Center of mass of the sources in this node.
+    center_of_mass: [f64; 2],
+
+    /// Index of the first of the four children in the arena, if this node was split.
+    children: Option<usize>,
+
+    /// The sources stored in this node, if it is a leaf.
+    bodies: Vec<usize>,
+}
+
+impl Node {
+    fn new(center: [f64; 2], half_size: f64) -> Node {
+        Node {
+            center,
+            half_size,
+            mass: 0.,
+            center_of_mass: [0., 0.],
+            children: None,
+            bodies: Vec::new(),
+        }
+    }
+
+    fn contains(&self, point: [f64; 2]) -> bool {
+        (point[0] - self.center[0]).abs() <= self.half_size
+            && (point[1] - self.center[1]).abs() <= self.half_size
+    }
+
+    /// Which of the four children a point falls in.
+    fn quadrant(&self, point: [f64; 2]) -> usize {
+        let east = (point[0] >= self.center[0]) as usize;
+        let north = (point[1] >= self.center[1]) as usize;
+        east + 2 * north
+    }
+}
+
+/// A quadtree over the sources, stored in an arena.
+struct QuadTree<'a> {
+    nodes: Vec<Node>,
+    positions: ArrayView2<'a, f64>,
+    masses: &'a [f64],
+}
+
+impl<'a> QuadTree<'a> {
+    fn build(positions: ArrayView2<'a, f64>, masses: &'a [f64]) -> QuadTree<'a> {
+        let mut min = [f64::INFINITY; 2];
+        let mut max = [f64::NEG_INFINITY; 2];
+        for i in 0..masses.len() {
+            for axis in 0..2 {
+                min[axis] = min[axis].min(positions[[i, axis]]);
+                max[axis] = max[axis].max(positions[[i, axis]]);
+            }
+        }
+
+        let center = [(min[0] + max[0]) / 2., (min[1] + max[1]) / 2.];
+        let half_size = ((max[0] - min[0]).max(max[1] - min[1]) / 2.).max(f64::MIN_POSITIVE);
+
+        let mut tree = QuadTree {
+            nodes: vec![Node::new(center, half_size)],
+            positions,
+            masses,
+        };
+
+        for body in 0..masses.len() {
+            tree.insert(body);
+        }
+
+        tree
+    }
+
+    fn position(&self, body: usize) -> [f64; 2] {
+        [self.positions[[body, 0]], self.positions[[body, 1]]]
+    }
+
+    fn insert(&mut self, body: usize) {
+        let position = self.position(body);
+        let mass = self.masses[body];
+        let mut node_index = 0;
+        let mut depth = 0;
+
+        loop {
+            let node = &mut self.nodes[node_index];
+
+            // Every node on the way down gains the body's mass.
+            let total_mass = node.mass + mass;
+            if total_mass > 0. {
+                for (center, p) in node.center_of_mass.iter_mut().zip(position) {
+                    *center = (*center * node.mass + p * mass) / total_mass;
+                }
+            }
+            node.mass = total_mass;
+
+            match node.children {
+                Some(first_child) => {
+                    node_index = first_child + node.quadrant(position);
+                    depth += 1;
+                }
+                None if node.bodies.is_empty() || depth >= MAX_DEPTH => {
+                    node.bodies.push(body);
+                    return;
+                }
+                None => {
+                    // Split the leaf, and push its body one level down.
+                    let first_child = self.split(node_index);
+                    let resident = self.nodes[node_index].bodies.pop().unwrap();
+                    let resident_position = self.position(resident);
+                    let child = first_child + self.nodes[node_index].quadrant(resident_position);
+                    let child_node = &mut self.nodes[child];
+                    child_node.bodies.push(resident);
+                    child_node.mass = self.masses[resident];
+                    child_node.center_of_mass = resident_position;
+
+                    node_index = first_child + self.nodes[node_index].quadrant(position);
+                    depth += 1;
+                }
+            }
+        }
+    }
+
+    /// Creates the four children of a node, and returns the index of the first one.
+    fn split(&mut self, node_index: usize) -> usize {
+        let first_child = self.nodes.len();
+        let center = self.nodes[node_index].center;
+        let quarter = self.nodes[node_index].half_size / 2.;
+
+        for quadrant in 0..4 {
+            let east = if quadrant % 2 == 1 { 1. } else { -1. };
+            let north = if quadrant / 2 == 1 { 1. } else { -1. };
+            self.nodes.push(Node::new(
+                [center[0] + east * quarter, center[1] + north * quarter],
+                quarter,
+            ));
+        }
+
+        self.nodes[node_index].children = Some(first_child);
+        first_child
+    }
+
+    /// Field at `point`. If `body` is a source, it is excluded from the sum.
+    fn field_at(
+        &self,
+        point: [f64; 2],
+        body: Option<usize>,
+        theta: f64,
+        softening: f64,
+    ) -> [f64; 2] {
+        let mut field = [0., 0.];
+        let mut stack = vec![0];
+
+        let mut add = |mass: f64, source: [f64; 2]| {
+            let dx = source[0] - point[0];
+            let dy = source[1] - point[1];
+            let inv_r3 = (dx.powi(2) + dy.powi(2) + softening.powi(2)).powf(-1.5);
+            field[0] += mass * dx * inv_r3;
+            field[1] += mass * dy * inv_r3;
+        };
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if node.mass == 0. {
+                continue;
+            }
+
+            match node.children {
+                None => {
+                    for &source in node.bodies.iter() {
+                        if Some(source) != body {
+                            add(self.masses[source], self.position(source));
+                        }
+                    }
+                }
+                Some(first_child) => {
+                    let dx = node.center_of_mass[0] - point[0];
+                    let dy = node.center_of_mass[1] - point[1];
+                    let distance = (dx.powi(2) + dy.powi(2)).sqrt();
+
+                    // A node holding the body itself can never be approximated,
+                    // or the body would attract itself.
+                    let holds_body = body.is_some() && node.contains(point);
+
+                    if !holds_body && 2. * node.half_size < theta * distance {
+                        add(node.mass, node.center_of_mass);
+                    } else {
+                        stack.extend(first_child..first_child + 4);
+                    }
+                }
+            }
+        }
+
+        field
+    }
+}
+
+/// Barnes-Hut tree solver (see https://en.wikipedia.org/wiki/Barnes%E2%80%93Hut_simulation)
+///
+/// Distant groups of sources are replaced by their center of mass, which
+/// brings the cost down to O(N log N).
+pub struct BarnesHutSolver {
+    /// The opening angle. A node is approximated when its size divided by its
+    /// distance is under this value. 0 is exact, larger values are faster.
+    pub theta: f64,
+}
+
+impl BarnesHutSolver {
+    pub fn new(theta: f64) -> BarnesHutSolver {
+        BarnesHutSolver { theta }
+    }
+}
+
+impl GravitySolver for BarnesHutSolver {
+    fn field(&self, positions: ArrayView2<f64>, masses: &[f64], softening: f64) -> Array2<f64> {
+        let mut field = Array2::zeros((positions.nrows(), 2));
+        if masses.is_empty() {
+            return field;
+        }
+
+        let tree = QuadTree::build(positions.view(), masses);
+
+        for (i, position) in positions.outer_iter().enumerate() {
+            let body = if i < masses.len() { Some(i) } else { None };
+            let field_i = tree.field_at([position[0], position[1]], body, self.theta, softening);
+            field[[i, 0]] = field_i[0];
+            field[[i, 1]] = field_i[1];
+        }
+
+        field
+    }
+}
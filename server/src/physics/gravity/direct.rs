@@ -0,0 +1,29 @@
+use ndarray::{Array2, ArrayView2};
+
+use super::solver::GravitySolver;
+
+/// Direct summation over every pair of bodies. O(N²), but exact.
+pub struct DirectSolver;
+
+impl GravitySolver for DirectSolver {
+    fn field(&self, positions: ArrayView2<f64>, masses: &[f64], softening: f64) -> Array2<f64> {
+        let mut field = Array2::zeros((positions.nrows(), 2));
+
+        for (i, position_i) in positions.outer_iter().enumerate() {
+            for (j, mass_j) in masses.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+
+                let dx = positions[[j, 0]] - position_i[0];
+                let dy = positions[[j, 1]] - position_i[1];
+
+                let inv_r3 = (dx.powi(2) + dy.powi(2) + softening.powi(2)).powf(-1.5);
+                field[[i, 0]] += mass_j * dx * inv_r3;
+                field[[i, 1]] += mass_j * dy * inv_r3;
+            }
+        }
+
+        field
+    }
+}
@@ -0,0 +1,3 @@
+pub mod barnes_hut;
+pub mod direct;
+pub mod solver;
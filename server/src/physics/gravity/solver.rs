@@ -0,0 +1,13 @@
+use ndarray::{Array2, ArrayView2};
+
+/// Computes the gravitational field felt by a set of bodies.
+pub trait GravitySolver: Send {
+    /// Returns, for every row of `positions`, the sum of `m * d / (r² + softening²)^1.5`
+    /// over all sources, where `d` points from the body to the source.
+    /// The result still has to be multiplied by G.
+    ///
+    /// The first `masses.len()` rows of `positions` are the sources, with the given masses.
+    /// The remaining rows are test particles, which feel gravity without exerting it.
+    /// A source never attracts itself.
+    fn field(&self, positions: ArrayView2<f64>, masses: &[f64], softening: f64) -> Array2<f64>;
+}
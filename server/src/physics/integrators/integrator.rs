@@ -0,0 +1,36 @@
+use common::data::settings::IntegratorKind;
+use ndarray::Array2;
+
+use super::{leapfrog::Leapfrog, rk4::RK4, rkf45::RKF45, verlet::Verlet};
+
+/// Positions, velocities and accelerations of every integrated body.
+/// Each array has one row per body, and one column per axis.
+#[derive(Debug, Clone)]
+pub struct Phase {
+    pub positions: Array2<f64>,
+    pub velocities: Array2<f64>,
+
+    /// Accelerations at the current positions and velocities.
+    /// Integrators must leave this up to date when they return.
+    pub accelerations: Array2<f64>,
+}
+
+/// Computes the accelerations of every body from their positions and velocities.
+pub type AccelerationFn<'a> = dyn Fn(&Array2<f64>, &Array2<f64>) -> Array2<f64> + 'a;
+
+/// A numerical integration scheme.
+pub trait Integrator: Send {
+    /// Advances `phase` by exactly `dt` seconds.
+    fn integrate(&mut self, phase: &mut Phase, dt: f64, acceleration: &AccelerationFn);
+}
+
+/// Creates the integrator matching `kind`.
+/// `tolerance` is only used by adaptive integrators.
+pub fn new_integrator(kind: IntegratorKind, tolerance: f64) -> Box<dyn Integrator> {
+    match kind {
+        IntegratorKind::Leapfrog => Box::new(Leapfrog),
+        IntegratorKind::Verlet => Box::new(Verlet),
+        IntegratorKind::RK4 => Box::new(RK4),
+        IntegratorKind::RKF45 => Box::new(RKF45::new(tolerance)),
+    }
+}
@@ -0,0 +1,16 @@
+use super::integrator::{AccelerationFn, Integrator, Phase};
+
+/// Leapfrog integration (see https://en.wikipedia.org/wiki/Leapfrog_integration#Algorithm)
+/// Kick-Drift-Kick form
+pub struct Leapfrog;
+
+impl Integrator for Leapfrog {
+    fn integrate(&mut self, phase: &mut Phase, dt: f64, acceleration: &AccelerationFn) {
+        phase.velocities += &(&phase.accelerations * dt / 2.);
+        phase.positions += &(&phase.velocities * dt);
+
+        phase.accelerations = acceleration(&phase.positions, &phase.velocities);
+
+        phase.velocities += &(&phase.accelerations * dt / 2.);
+    }
+}
@@ -0,0 +1,5 @@
+pub mod integrator;
+pub mod leapfrog;
+pub mod rk4;
+pub mod rkf45;
+pub mod verlet;
@@ -0,0 +1,31 @@
+use super::integrator::{AccelerationFn, Integrator, Phase};
+
+/// Classic fourth order Runge-Kutta (see https://en.wikipedia.org/wiki/Runge%E2%80%93Kutta_methods)
+///
+/// Costs four acceleration evaluations per step, but is much more accurate than
+/// leapfrog for close approaches and velocity dependent forces.
+pub struct RK4;
+
+impl Integrator for RK4 {
+    fn integrate(&mut self, phase: &mut Phase, dt: f64, acceleration: &AccelerationFn) {
+        let x = &phase.positions;
+        let v = &phase.velocities;
+
+        let k1_x = v.clone();
+        let k1_v = acceleration(x, v);
+
+        let k2_x = v + &(&k1_v * (dt / 2.));
+        let k2_v = acceleration(&(x + &(&k1_x * (dt / 2.))), &k2_x);
+
+        let k3_x = v + &(&k2_v * (dt / 2.));
+        let k3_v = acceleration(&(x + &(&k2_x * (dt / 2.))), &k3_x);
+
+        let k4_x = v + &(&k3_v * dt);
+        let k4_v = acceleration(&(x + &(&k3_x * dt)), &k4_x);
+
+        phase.positions += &((k1_x + &(k2_x * 2.) + &(k3_x * 2.) + &k4_x) * (dt / 6.));
+        phase.velocities += &((k1_v + &(k2_v * 2.) + &(k3_v * 2.) + &k4_v) * (dt / 6.));
+
+        phase.accelerations = acceleration(&phase.positions, &phase.velocities);
+    }
+}
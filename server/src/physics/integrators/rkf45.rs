@@ -0,0 +1,151 @@
+use ndarray::Array2;
+
+use super::integrator::{AccelerationFn, Integrator, Phase};
+
+// Butcher tableau for Runge-Kutta-Fehlberg 4(5).
+// See https://en.wikipedia.org/wiki/Runge%E2%80%93Kutta%E2%80%93Fehlberg_method
+const A: [[f64; 5]; 6] = [
+    [0., 0., 0., 0., 0.],
+    [1. / 4., 0., 0., 0., 0.],
+    [3. / 32., 9. / 32., 0., 0., 0.],
+    [1932. / 2197., -7200. / 2197., 7296. / 2197., 0., 0.],
+    [439. / 216., -8., 3680. / 513., -845. / 4104., 0.],
+    [-8. / 27., 2., -3544. / 2565., 1859. / 4104., -11. / 40.],
+];
+
+/// Weights of the fourth order solution, which is the one kept.
+const B4: [f64; 6] = [25. / 216., 0., 1408. / 2565., 2197. / 4104., -1. / 5., 0.];
+
+/// Weights of the fifth order solution, only used to estimate the error.
+const B5: [f64; 6] = [
+    16. / 135.,
+    0.,
+    6656. / 12825.,
+    28561. / 56430.,
+    -9. / 50.,
+    2. / 55.,
+];
+
+/// Safety factor applied to the optimal step size.
+const SAFETY: f64 = 0.9;
+
+/// Bounds on how much the step size may change after a single step.
+const MIN_SCALE: f64 = 0.2;
+const MAX_SCALE: f64 = 5.0;
+
+/// Steps smaller than this fraction of the requested `dt` are accepted whatever their error,
+/// so a singularity can't stall the physics thread.
+const MIN_STEP_FRACTION: f64 = 1e-6;
+
+/// Adaptive Runge-Kutta-Fehlberg 4(5).
+///
+/// Each call to `integrate` is split into as many substeps as needed to keep the
+/// estimated local error under `tolerance`. The last step size is remembered
+/// between calls, so long quiet cruises quickly settle on large steps.
+/// The last substep is shortened to end exactly at `dt`, without changing the remembered size.
+pub struct RKF45 {
+    /// Maximum local error per substep, in meters.
+    tolerance: f64,
+    step_size: Option<f64>,
+}
+
+impl RKF45 {
+    pub fn new(tolerance: f64) -> RKF45 {
+        RKF45 {
+            tolerance,
+            step_size: None,
+        }
+    }
+
+    /// Tries a single step of size `h`.
+    /// Returns the fourth order positions and velocities, and the estimated error.
+    fn attempt(
+        &self,
+        phase: &Phase,
+        h: f64,
+        acceleration: &AccelerationFn,
+    ) -> (Array2<f64>, Array2<f64>, f64) {
+        let mut k_x: Vec<Array2<f64>> = Vec::with_capacity(6);
+        let mut k_v: Vec<Array2<f64>> = Vec::with_capacity(6);
+
+        for (stage, a) in A.iter().enumerate() {
+            let mut x = phase.positions.clone();
+            let mut v = phase.velocities.clone();
+            for j in 0..stage {
+                if a[j] != 0. {
+                    x.scaled_add(h * a[j], &k_x[j]);
+                    v.scaled_add(h * a[j], &k_v[j]);
+                }
+            }
+
+            // The first stage reuses the accelerations we already know.
+            let acc = if stage == 0 {
+                phase.accelerations.clone()
+            } else {
+                acceleration(&x, &v)
+            };
+            k_x.push(v);
+            k_v.push(acc);
+        }
+
+        let mut x = phase.positions.clone();
+        let mut v = phase.velocities.clone();
+        let mut error_x = Array2::<f64>::zeros(x.raw_dim());
+        let mut error_v = Array2::<f64>::zeros(v.raw_dim());
+
+        for i in 0..6 {
+            x.scaled_add(h * B4[i], &k_x[i]);
+            v.scaled_add(h * B4[i], &k_v[i]);
+            error_x.scaled_add(h * (B5[i] - B4[i]), &k_x[i]);
+            error_v.scaled_add(h * (B5[i] - B4[i]), &k_v[i]);
+        }
+
+        // A velocity error turns into a position error of about `error_v * h` by the next step.
+        let error = error_x
+            .iter()
+            .map(|e| e.abs())
+            .chain(error_v.iter().map(|e| (e * h).abs()))
+            .fold(0.0, f64::max);
+
+        (x, v, error)
+    }
+}
+
+impl Integrator for RKF45 {
+    fn integrate(&mut self, phase: &mut Phase, dt: f64, acceleration: &AccelerationFn) {
+        let min_step = dt * MIN_STEP_FRACTION;
+        let mut remaining = dt;
+
+        // The step size the error calls for, before it is fitted to what remains of `dt`.
+        let mut h = self.step_size.unwrap_or(dt).max(min_step);
+
+        while remaining > 0. {
+            // A tail too short for a step of its own is taken with the last step.
+            let last = remaining - h <= min_step;
+            let step = if last { remaining } else { h };
+
+            let (x, v, error) = self.attempt(phase, step, acceleration);
+
+            let accepted = error <= self.tolerance || step <= min_step;
+            if accepted {
+                phase.positions = x;
+                phase.velocities = v;
+                phase.accelerations = acceleration(&phase.positions, &phase.velocities);
+                remaining = if last { 0. } else { remaining - step };
+            }
+
+            // A step fitted to the end of `dt` says nothing about the size that was called for,
+            // unless it failed.
+            if step == h || !accepted {
+                let scale = if error > 0. {
+                    SAFETY * (self.tolerance / error).powf(0.2)
+                } else {
+                    MAX_SCALE
+                };
+                h = (step * scale.clamp(MIN_SCALE, MAX_SCALE)).max(min_step);
+            }
+        }
+
+        self.step_size = Some(h);
+    }
+}
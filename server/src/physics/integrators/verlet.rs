@@ -0,0 +1,18 @@
+use super::integrator::{AccelerationFn, Integrator, Phase};
+
+/// Velocity Verlet integration (see https://en.wikipedia.org/wiki/Verlet_integration#Velocity_Verlet)
+///
+/// Positions are advanced with the full second order term, then velocities
+/// are advanced with the average of the old and new accelerations.
+pub struct Verlet;
+
+impl Integrator for Verlet {
+    fn integrate(&mut self, phase: &mut Phase, dt: f64, acceleration: &AccelerationFn) {
+        phase.positions += &(&phase.velocities * dt + &phase.accelerations * (dt * dt / 2.));
+
+        let new_accelerations = acceleration(&phase.positions, &phase.velocities);
+        phase.velocities += &((&phase.accelerations + &new_accelerations) * (dt / 2.));
+
+        phase.accelerations = new_accelerations;
+    }
+}
@@ -1,3 +1,9 @@
+pub mod attitude;
+pub mod collisions;
+pub mod forces;
+pub mod gravity;
+pub mod integrators;
 pub mod physics_engine;
 pub mod physics_runner;
 pub mod state_loader;
+pub mod time_warp;
@@ -1,133 +1,172 @@
 use ndarray::{s, Array2};
 
 use common::data::{
-    inputs::Inputs,
+    clock::{Clock, WARP_LEVELS},
+    event::Event,
+    inputs::{inputs_for, ShipInputs},
     object::{Objects, ShipType},
 };
 
-const G: f64 = 6.674e-11;
+use super::{
+    attitude::steer,
+    collisions::resolve_collisions,
+    forces::ForceModel,
+    integrators::integrator::{Integrator, Phase},
+    time_warp::max_warp,
+};
 
 pub struct PhysicsEngine {
-    pub softening: f64,
+    pub forces: ForceModel,
+    integrator: Box<dyn Integrator>,
+
+    /// Impacts faster than this, in meters per second, are crashes.
+    pub crash_speed: f64,
 }
 
 impl PhysicsEngine {
-    pub fn new(softening: f64) -> PhysicsEngine {
-        PhysicsEngine { softening }
+    pub fn new(
+        forces: ForceModel,
+        integrator: Box<dyn Integrator>,
+        crash_speed: f64,
+    ) -> PhysicsEngine {
+        PhysicsEngine {
+            forces,
+            integrator,
+            crash_speed,
+        }
     }
 
-    fn get_planet_accelerations(&self, objects: &mut Objects) -> Array2<f64> {
-        let (_, planets) = objects;
-        let mut planet_accelerations = Array2::zeros((planets.len(), 2));
-
-        // TODO: Vectorize this if this is slow.
-        // Note: Extracting this into its own function would require me to add a lot
-        // of code to generalize both the Planet and the Ship t
-
-        for (planet_i_index, planet_i) in planets.iter().enumerate() {
-            for (planet_j_index, planet_j) in planets.iter().enumerate() {
-                if planet_i_index == planet_j_index {
-                    continue;
-                }
-
-                let distance = &planet_j.object.position - &planet_i.object.position;
-                let dx = distance[0];
-                let dy = distance[1];
-
-                // Needed to get the acceleration of the ship in the x and y axis, in a mutable ndarray.
-                // This value gets operations peformed on it.
-                let mut acceleration = planet_accelerations.slice_mut(s![planet_i_index, ..]);
-                let inv_r3 = (dx.powi(2) + dy.powi(2) + self.softening.powi(2)).powf(-1.5);
-                acceleration[0] += G * planet_j.object.mass * &(dx) * inv_r3;
-                acceleration[1] += G * planet_j.object.mass * &(dy) * inv_r3;
-            }
+    /// Copies the position, velocity and acceleration of every object into a `Phase`.
+    /// Planets come first, then ships.
+    fn get_phase(objects: &Objects) -> Phase {
+        let (ships, planets) = objects;
+        let count = planets.len() + ships.len();
+        let mut phase = Phase {
+            positions: Array2::zeros((count, 2)),
+            velocities: Array2::zeros((count, 2)),
+            accelerations: Array2::zeros((count, 2)),
+        };
+
+        let bodies = planets
+            .iter()
+            .map(|p| &p.object)
+            .chain(ships.iter().map(|s| &s.object));
+        for (i, object) in bodies.enumerate() {
+            phase
+                .positions
+                .slice_mut(s![i, ..])
+                .assign(&object.position);
+            phase
+                .velocities
+                .slice_mut(s![i, ..])
+                .assign(&object.velocity);
+            phase
+                .accelerations
+                .slice_mut(s![i, ..])
+                .assign(&object.acceleration);
         }
 
-        planet_accelerations
+        phase
     }
 
-    fn get_ship_accelerations(&self, objects: &Objects, inputs: &Inputs) -> Array2<f64> {
+    /// Writes an integrated `Phase` back into the objects.
+    fn set_phase(objects: &mut Objects, phase: &Phase) {
         let (ships, planets) = objects;
-        let mut ship_accelerations = Array2::zeros((ships.len(), 2));
+        let bodies = planets
+            .iter_mut()
+            .map(|p| &mut p.object)
+            .chain(ships.iter_mut().map(|s| &mut s.object));
+
+        for (i, object) in bodies.enumerate() {
+            object.position = phase.positions.slice(s![i, ..]).to_owned();
+            object.velocity = phase.velocities.slice(s![i, ..]).to_owned();
+            object.acceleration = phase.accelerations.slice(s![i, ..]).to_owned();
+        }
+    }
 
-        // TODO: Vectorize this if this is slow.
+    /// Turns every object by its spin. Landed ships turn with their planet instead.
+    fn rotate(objects: &mut Objects, dt: f64) {
+        let (ships, planets) = objects;
 
-        for (ship_index, ship) in ships.iter().enumerate() {
-            for planet in planets {
-                let distance = &planet.object.position - &ship.object.position;
-                let dx = distance[0];
-                let dy = distance[1];
+        for planet in planets.iter_mut() {
+            planet.object.heading += planet.object.spin * dt;
+        }
 
-                // Needed to get the acceleration of the ship in the x and y axis, in a mutable ndarray.
-                // This value gets operations peformed on it.
-                let mut ship_acceleration = ship_accelerations.slice_mut(s![ship_index, ..]);
-                let inv_r3 = (dx.powi(2) + dy.powi(2) + self.softening.powi(2)).powf(-1.5);
-                let acceleration_from_j = planet.object.mass * &(distance) * inv_r3;
+        for ship in ships.iter_mut() {
+            let planet_spin = ship.landed.as_ref().and_then(|landing| {
+                planets
+                    .iter()
+                    .find(|p| p.object.id == landing.planet)
+                    .map(|p| p.object.spin)
+            });
 
-                ship_acceleration += &acceleration_from_j;
-            }
+            ship.object.heading += planet_spin.unwrap_or(ship.object.spin) * dt;
         }
+    }
 
-        for (ship_index, ship) in ships.iter().enumerate() {
-            match &ship.ship_type {
+    /// Burns the fuel used by every thrusting ship over `dt` seconds, and updates their mass.
+    fn burn_fuel(objects: &mut Objects, inputs: &ShipInputs, dt: f64) {
+        for ship in objects.0.iter_mut() {
+            let throttle = inputs_for(inputs, ship.object.id).throttle;
+
+            match &mut ship.ship_type {
                 ShipType::HAB(data) => {
-                    let mut acceleration = ship_accelerations.slice_mut(s![ship_index, ..]);
-                    let heading_vector =
-                        ndarray::arr1(&[ship.object.heading.cos(), ship.object.heading.sin()]);
-                    let acc_from_engine = heading_vector * data.thrust as f64 * inputs.throttle as f64;
-                    acceleration += &acc_from_engine;
+                    let burnt = data.mass_flow() * throttle as f64 * dt;
+                    data.fuel_mass = (data.fuel_mass - burnt).max(0.);
                 }
             }
-        }
 
-        ship_accelerations
+            ship.object.mass = ship.mass();
+        }
     }
 
-    /// Gets ships' acceleration, and integrates to get each ships' position
-    fn step_ships(&mut self, objects: &mut Objects, inputs: &Inputs, dt: f64) {
-        for ship in objects.0.iter_mut() {
-            ship.object.velocity += &(&ship.object.acceleration * dt / 2.);
-            ship.object.position += &(&ship.object.velocity * dt);
-        }
+    /// Integrates and gets the new positions, using the configured integrator.
+    /// Takes `steps` steps, resolving collisions after each one, and advances `clock`.
+    ///
+    /// Each step lasts `timestep` times the warp. Before every step, the requested warp
+    /// is capped by `max_warp`, so the warp only depends on the state of the simulation.
+    ///
+    /// Returns the events raised during the steps.
+    pub fn step(
+        &mut self,
+        objects: &mut Objects,
+        inputs: &ShipInputs,
+        clock: &mut Clock,
+        timestep: f64,
+        steps: u32,
+    ) -> Vec<Event> {
+        let mut phase = Self::get_phase(objects);
+        let mut events = Vec::new();
+
+        for _ in 0..steps {
+            let warp = clock
+                .requested_warp
+                .min(max_warp(objects, &phase, inputs, timestep));
+            if clock.warp != warp {
+                log::info!("Time warp is now {}x", WARP_LEVELS[warp]);
+            }
+            let dt = timestep * WARP_LEVELS[warp];
 
-        let accelerations = self.get_ship_accelerations(objects, inputs);
+            let forces = &self.forces;
+            let frozen_objects: &Objects = objects;
+            let acceleration = |positions: &Array2<f64>, velocities: &Array2<f64>| {
+                forces.get_accelerations(frozen_objects, inputs, positions, velocities)
+            };
 
-        for (i, ship) in objects.0.iter_mut().enumerate() {
-            ship.object.acceleration = accelerations.slice(s![i, ..]).to_owned();
-        }
+            self.integrator.integrate(&mut phase, dt, &acceleration);
 
-        for ship in objects.0.iter_mut() {
-            ship.object.velocity += &(&ship.object.acceleration * dt / 2.);
-        }
-    }
+            Self::burn_fuel(objects, inputs, dt);
+            steer(objects, &phase, inputs, dt);
+            Self::rotate(objects, dt);
+            resolve_collisions(objects, &mut phase, self.crash_speed, &mut events);
 
-    /// Gets planets' acceleration, and integrates to get each planets' position
-    fn step_planets(&mut self, objects: &mut Objects, dt: f64) {
-        for planet in objects.1.iter_mut() {
-            let acc = &planet.object.acceleration;
-            planet.object.velocity += &(acc * dt / 2.);
-            planet.object.position += &(&planet.object.velocity * dt);
+            clock.time += dt;
+            clock.step += 1;
+            clock.warp = warp;
         }
-        
-        let accelerations = self.get_planet_accelerations(objects);
 
-        for (i, ship) in objects.1.iter_mut().enumerate() {
-            ship.object.acceleration = accelerations.slice(s![i, ..]).to_owned();
-        }
-        
-        for planet in objects.1.iter_mut() {
-            let acc = &planet.object.acceleration;
-            planet.object.velocity += &(acc * dt / 2.);
-        }
-    }
+        Self::set_phase(objects, &phase);
 
-    /// Integrates and gets the new positions.
-    /// Uses leapfrog integration (see https://en.wikipedia.org/wiki/Leapfrog_integration#Algorithm)
-    /// Kick-Drift-Kick form
-    pub fn step(&mut self, objects: &mut Objects, inputs: &Inputs, dt: f64, steps_per_frame: u32) {
-        for _ in 0..steps_per_frame {
-            self.step_planets(objects, dt / steps_per_frame as f64);
-            self.step_ships(objects, inputs, dt / steps_per_frame as f64);
-        }
+        events
     }
 }
@@ -1,49 +1,62 @@
-use std::sync::{RwLock, Arc};
+use std::{
+    sync::{mpsc::Sender, Arc, RwLock},
+    thread,
+    time::{Duration, Instant},
+};
 
-use common::data::{object::{Ship, Planet}, inputs::Inputs};
+use common::data::{
+    clock::Clock,
+    event::Event,
+    inputs::ShipInputs,
+    object::{Planet, Ship},
+};
 
 use super::physics_engine::PhysicsEngine;
 
-
-
 pub struct PhysicsConfig {
+    /// How many times per second the physics thread wakes up and catches up with real time.
     pub target_updates_per_s: u32,
-    pub min_spu: u32,
-    pub max_spu: Option<u32>,
+
+    /// Length of a step in simulated seconds, at 1x warp.
+    pub timestep: f64,
+
+    /// If catching up would take more steps than this, the extra time is dropped.
+    pub max_steps_per_update: u32,
 }
 
+/// Runs the simulation with a fixed timestep.
+///
+/// Real time is accumulated, and consumed in whole steps of `timestep`.
+/// Each step advances the simulation by `timestep` times the warp in effect for that step,
+/// so a run is reproducible from its system file, inputs and warp requests.
 pub fn run_physics(
     physics: &mut PhysicsEngine,
     objects_rwlock: Arc<RwLock<(Vec<Ship>, Vec<Planet>)>>,
-    inputs_rwlock_clone: Arc<RwLock<Inputs>>,
+    inputs_rwlock_clone: Arc<RwLock<ShipInputs>>,
+    clock_rwlock: Arc<RwLock<Clock>>,
+    events_tx: Sender<Event>,
     config: PhysicsConfig,
 ) {
-    let mut steps_per_update: u32 = 5;
-    let mut prev_time = std::time::Instant::now();
-    let mut first_frame = true;
+    let update_duration = Duration::from_secs_f64(1.0 / config.target_updates_per_s as f64);
+    let mut accumulator = 0.0;
+    let mut prev_time = Instant::now();
 
     loop {
-        let now = std::time::Instant::now();
-        let dt = now.duration_since(prev_time).as_secs_f64();
+        let now = Instant::now();
+        accumulator += now.duration_since(prev_time).as_secs_f64();
         prev_time = now;
 
-        // dt can sometimes be 0, which causes a divide by zero error
-        let fps = (if dt > 0.0 { 1.0 / dt } else { 0.0 }) as u32;
-        log::trace!("FPS: {}", fps);
+        let mut steps = (accumulator / config.timestep) as u32;
+        accumulator -= steps as f64 * config.timestep;
 
-        if !first_frame {
-            // Tune the number of steps to try to hit the target updates per second
-            let spu_unclamped = (steps_per_update + fps - config.target_updates_per_s) as u32;
-
-            if let Some(max) = config.max_spu {
-                steps_per_update = spu_unclamped.clamp(config.min_spu, max);
-            } else {
-                steps_per_update = spu_unclamped.max(config.min_spu);
-            }
+        if steps > config.max_steps_per_update {
+            log::warn!(
+                "Physics is falling behind, dropping {} steps",
+                steps - config.max_steps_per_update
+            );
+            steps = config.max_steps_per_update;
         }
-        log::trace!("SPU: {}", steps_per_update);
-
-        first_frame = false;
+        log::trace!("Steps this update: {}", steps);
 
         let objects_lock = objects_rwlock.read().unwrap();
         let mut objects = objects_lock.clone();
@@ -53,9 +66,28 @@ pub fn run_physics(
         let inputs = inputs_lock.clone();
         drop(inputs_lock);
 
-        physics.step(&mut objects, &inputs, dt, steps_per_update);
+        let mut clock = clock_rwlock.read().unwrap().clone();
+
+        let events = physics.step(&mut objects, &inputs, &mut clock, config.timestep, steps);
 
         let mut object_w_lock = objects_rwlock.write().unwrap();
         *object_w_lock = objects;
+        drop(object_w_lock);
+
+        // Clients may have requested another warp in the meantime, so only the
+        // fields the steps advanced are written back.
+        let mut clock_w_lock = clock_rwlock.write().unwrap();
+        clock_w_lock.time = clock.time;
+        clock_w_lock.step = clock.step;
+        clock_w_lock.warp = clock.warp;
+        drop(clock_w_lock);
+
+        for event in events {
+            if events_tx.send(event).is_err() {
+                log::error!("Failed to send event to the network thread.");
+            }
+        }
+
+        thread::sleep(update_duration.saturating_sub(now.elapsed()));
     }
-}
\ No newline at end of file
+}
@@ -11,7 +11,7 @@ pub fn load_state(path: &str) -> State {
         }
     };
     
-    let state: State = match ron::from_str(system_file.as_str()) {
+    let mut state: State = match ron::from_str(system_file.as_str()) {
         Ok(val) => val,
         Err(e) => {
             log::error!("Failed to parse system file: {}", e);
@@ -19,5 +19,10 @@ pub fn load_state(path: &str) -> State {
         }
     };
 
+    // Ship masses always follow their dry mass and fuel.
+    for ship in state.ships.iter_mut() {
+        ship.object.mass = ship.mass();
+    }
+
     state
 }
\ No newline at end of file
@@ -0,0 +1,57 @@
+use common::data::{
+    clock::WARP_LEVELS,
+    inputs::{inputs_for, ShipInputs},
+    object::Objects,
+};
+
+use super::integrators::integrator::Phase;
+
+/// The highest warp level allowed while any ship is thrusting.
+const MAX_WARP_UNDER_THRUST: usize = 1;
+
+/// Near a planet, a single step may move a ship by at most this fraction of its altitude.
+/// Beyond that, the integrator would step over the approach, or into the surface.
+const MAX_STEP_PER_ALTITUDE: f64 = 0.01;
+
+/// Finds the highest warp level that keeps the simulation accurate,
+/// with the objects where `phase` has them.
+///
+/// `timestep` is the length of a step at 1x warp. Every warp level multiplies it.
+pub fn max_warp(objects: &Objects, phase: &Phase, inputs: &ShipInputs, timestep: f64) -> usize {
+    let (ships, planets) = objects;
+    let mut max_warp = WARP_LEVELS.len() - 1;
+
+    let thrusting = ships
+        .iter()
+        .any(|ship| inputs_for(inputs, ship.object.id).throttle > 0. && ship.delta_v() > 0.);
+    if thrusting {
+        max_warp = max_warp.min(MAX_WARP_UNDER_THRUST);
+    }
+
+    for (ship_index, ship) in ships.iter().enumerate() {
+        // A landed ship moves with the surface, however fast it is carried around the planet.
+        if ship.landed.is_some() {
+            continue;
+        }
+
+        let ship_row = planets.len() + ship_index;
+        for (planet_row, planet) in planets.iter().enumerate() {
+            let offset = &phase.positions.row(ship_row) - &phase.positions.row(planet_row);
+            let altitude = offset.dot(&offset).sqrt() - planet.radius;
+
+            let relative_velocity =
+                &phase.velocities.row(ship_row) - &phase.velocities.row(planet_row);
+            let speed = relative_velocity.dot(&relative_velocity).sqrt();
+
+            // Highest level at which a step stays small compared to the altitude.
+            let allowed = WARP_LEVELS
+                .iter()
+                .rposition(|warp| speed * timestep * warp <= MAX_STEP_PER_ALTITUDE * altitude)
+                .unwrap_or(0);
+
+            max_warp = max_warp.min(allowed);
+        }
+    }
+
+    max_warp
+}